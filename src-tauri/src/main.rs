@@ -38,6 +38,7 @@ use tokio::io::{AsyncBufReadExt, BufReader};
 
 use std::process::Child;
 use std::sync::Mutex;
+use std::collections::HashMap;
 use tauri::State;
 
 use tauri::AppHandle;
@@ -45,8 +46,44 @@ use tauri_plugin_shell::process::CommandChild;
 
 
 
-//  Updated state to hold the Tauri Sidecar CommandChild
-pub struct ExportState(pub Mutex<Option<CommandChild>>);
+//  Export queue, with the Tauri Sidecar CommandChild of every running job
+pub struct ExportState(pub Mutex<ExportQueue>);
+
+// Jobs in the order they were added; up to `concurrency` of them run at the same time.
+// `running` holds the ffmpeg process of the pass each running job is in, `prepared` the
+// graphs export_video already built for the jobs that did not start yet.
+pub struct ExportQueue {
+    pub jobs: Vec<ExportJob>,
+    pub running: HashMap<String, CommandChild>,
+    pub prepared: HashMap<String, PreparedExport>,
+    pub concurrency: usize,
+}
+
+// Proxy generation, one file at a time in the background. `running` is the file being
+// encoded and its ffmpeg process, `worker` tells whether the background task is alive.
+// `cancelled` is set by cancel_proxies for the file the worker took but did not start yet.
+pub struct ProxyState(pub Mutex<ProxyQueue>);
+
+#[derive(Default)]
+pub struct ProxyQueue {
+    pub pending: Vec<PathBuf>,
+    pub running: Option<(PathBuf, CommandChild)>,
+    pub worker: bool,
+    pub cancelled: bool,
+}
+
+// Guards the read-modify-write of media_info.json, probes run from several commands and
+// export jobs at once
+pub struct MediaInfoState(pub Mutex<()>);
+
+// Same for assets.json, written by imports and conforms that may run together
+pub struct AssetsState(pub Mutex<()>);
+
+impl Default for ExportQueue {
+    fn default() -> Self {
+        ExportQueue { jobs: Vec::new(), running: HashMap::new(), prepared: HashMap::new(), concurrency: 1 }
+    }
+}
 
 
 #[derive(serde::Serialize)]
@@ -55,7 +92,7 @@ struct Project {
     path: String
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Dimensions {
     pub x: f64, 
     pub y: f64,
@@ -67,6 +104,105 @@ pub struct VideoMetadata {
     duration: f64,
 }
 
+// Everything ffprobe reports about a file that the editor uses. `size` and `modified`
+// (seconds since the epoch) tell whether a cached probe still matches the file.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    pub path: String,
+    pub format_name: String,
+    pub duration: f64,
+    pub bit_rate: Option<u64>,
+    pub size: u64,
+    pub modified: u64,
+    pub streams: Vec<StreamInfo>,
+}
+
+// One stream of a file. `kind` is video, audio, subtitle or data; `rotation` is the display
+// rotation in degrees and `frameRate` the average one (variable frame rate files differ
+// from `realFrameRate`).
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamInfo {
+    pub index: u32,
+    pub kind: String,
+    pub codec: String,
+    pub profile: Option<String>,
+    pub duration: Option<f64>,
+    pub bit_rate: Option<u64>,
+    pub language: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub real_frame_rate: Option<f64>,
+    pub frame_count: Option<u64>,
+    pub pixel_format: Option<String>,
+    pub rotation: Option<f64>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub bit_depth: Option<u32>,
+}
+
+// What import_asset learned about a file, kept in assets.json of the project. When the file
+// was conformed to constant frame rate, videos/<name> is the conformed version and
+// `original` the untouched copy in originals/.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetRecord {
+    pub name: String,
+    pub source: String,
+    pub original: String,
+    pub conformed: Option<String>,
+    pub variable_frame_rate: bool,
+    // Share of frames whose duration differs from the previous one (vfrdet)
+    pub vfr_ratio: f64,
+    pub frame_rate: Option<f64>,
+    // Set when the frame rate could not be checked, the asset is then taken as constant
+    #[serde(default)]
+    pub warning: Option<String>,
+}
+
+// A numbered image sequence imported as one asset. The frames are copied to
+// sequences/<name>/ of the project and videos/<name>.imgseq holds this descriptor, so the
+// sequence is listed and placed on the timeline like any video file. `pattern` is the
+// printf style name of the frames (shot_%04d.exr).
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageSequence {
+    pub name: String,
+    pub directory: String,
+    pub pattern: String,
+    pub start_number: u64,
+    pub frame_count: u64,
+    pub frame_rate: f64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl MediaInfo {
+    pub fn video_stream(&self) -> Option<&StreamInfo> {
+        self.streams.iter().find(|s| s.kind == "video")
+    }
+
+    // A moving picture with a real timeline: not a still, sequence or GIF/WebP animation
+    pub fn is_video(&self) -> bool {
+        self.duration > 0.0
+            && self.video_stream().is_some()
+            && !self.format_name.contains("image2")
+            && !self.format_name.ends_with("_pipe")
+            && !matches!(self.format_name.as_str(), "gif" | "webp")
+    }
+
+    // Size as displayed, with the rotation applied
+    pub fn display_dimensions(&self) -> Option<Dimensions> {
+        let video = self.video_stream()?;
+        let (w, h) = (video.width? as f64, video.height? as f64);
+        let quarter_turn = video.rotation.map(|r| (r.abs() % 180.0 - 90.0).abs() < 1.0).unwrap_or(false);
+        Some(if quarter_turn { Dimensions { x: h, y: w } } else { Dimensions { x: w, y: h } })
+    }
+}
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -74,6 +210,87 @@ pub struct Keyframe {
     pub id: String,
     pub time: f64,
     pub value: f64,
+    // Curve from this keyframe to the next one
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    // Handles (x1, y1, x2, y2) of the curve when `interpolation` is bezier, as in CSS cubic-bezier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bezier: Option<[f64; 4]>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    #[serde(alias = "step")]
+    Hold,
+    #[serde(alias = "ease-in")]
+    EaseIn,
+    #[serde(alias = "ease-out")]
+    EaseOut,
+    #[serde(alias = "ease-in-out")]
+    EaseInOut,
+    #[serde(alias = "cubicBezier")]
+    Bezier,
+}
+
+// Angles in degrees. The timeline saves the in-plane rotation as `rot` and the
+// 3D (Y axis) rotation as `rot3d`; older projects saved them as `{x, y}` (x: rot, y: rot3d)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(from = "Rotation3dValue")]
+pub struct Rotation3d {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+// Every shape a rotation was saved in
+#[derive(Deserialize)]
+struct Rotation3dValue {
+    x: Option<f64>,
+    y: Option<f64>,
+    z: Option<f64>,
+    rot: Option<f64>,
+    rot3d: Option<f64>,
+}
+
+impl From<Rotation3dValue> for Rotation3d {
+    fn from(value: Rotation3dValue) -> Self {
+        let legacy = value.z.is_none() && value.rot.is_none() && value.rot3d.is_none();
+        if legacy {
+            // `{x, y}`: x is the in-plane rotation, not a tilt
+            return Rotation3d { x: 0.0, y: value.y.unwrap_or(0.0), z: value.x.unwrap_or(0.0) };
+        }
+        Rotation3d {
+            x: value.x.unwrap_or(0.0),
+            y: value.rot3d.or(value.y).unwrap_or(0.0),
+            z: value.rot.or(value.z).unwrap_or(0.0),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Rotation3dKeyframe {
+    pub id: String,
+    pub time: f64,
+    pub value: Rotation3d,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bezier: Option<[f64; 4]>,
+}
+
+// Top-left corner of the clip in canvas pixels, as the timeline drags it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PositionKeyframe {
+    pub id: String,
+    pub time: f64,
+    pub value: Dimensions,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bezier: Option<[f64; 4]>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -81,7 +298,197 @@ pub struct Keyframes {
     pub volume: Option<Vec<Keyframe>>,
     pub opacity: Option<Vec<Keyframe>>,
     pub speed: Option<Vec<Keyframe>>,
-    pub rotation3d: Option<Vec<Keyframe>>,
+    pub rotation3d: Option<Vec<Rotation3dKeyframe>>,
+
+    // Tracks of the timeline viewer: `zoom` multiplies the size of the asset and `position`
+    // is its top-left corner. Used by the export when the Transform tracks below are unset.
+    pub zoom: Option<Vec<Keyframe>>,
+    pub position: Option<Vec<PositionKeyframe>>,
+
+    // Transform tracks, see Transform
+    #[serde(rename = "positionX")]
+    pub position_x: Option<Vec<Keyframe>>,
+    #[serde(rename = "positionY")]
+    pub position_y: Option<Vec<Keyframe>>,
+    pub scale: Option<Vec<Keyframe>>,
+    #[serde(rename = "scaleX")]
+    pub scale_x: Option<Vec<Keyframe>>,
+    #[serde(rename = "scaleY")]
+    pub scale_y: Option<Vec<Keyframe>>,
+    pub rotation: Option<Vec<Keyframe>>,
+    #[serde(rename = "cropLeft")]
+    pub crop_left: Option<Vec<Keyframe>>,
+    #[serde(rename = "cropRight")]
+    pub crop_right: Option<Vec<Keyframe>>,
+    #[serde(rename = "cropTop")]
+    pub crop_top: Option<Vec<Keyframe>>,
+    #[serde(rename = "cropBottom")]
+    pub crop_bottom: Option<Vec<Keyframe>>,
+
+    // Color correction tracks, see ColorCorrection
+    pub exposure: Option<Vec<Keyframe>>,
+    pub contrast: Option<Vec<Keyframe>>,
+    pub saturation: Option<Vec<Keyframe>>,
+    pub temperature: Option<Vec<Keyframe>>,
+    pub tint: Option<Vec<Keyframe>>,
+}
+
+// Placement of a visual clip on the canvas. `x`/`y` are the canvas position (pixels) of the
+// anchor, given as a fraction of the clip (0.5/0.5 is its center). `scaleX`/`scaleY` multiply
+// the uniform `scale`, `rotation` is in degrees and the crop values are the fraction of the
+// clip hidden from each edge. Missing values keep the centered fit; any of them can be
+// animated with the track of the same name in Keyframes.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Transform {
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+    pub scale: Option<f64>,
+    #[serde(rename = "scaleX")]
+    pub scale_x: Option<f64>,
+    #[serde(rename = "scaleY")]
+    pub scale_y: Option<f64>,
+    #[serde(rename = "anchorX")]
+    pub anchor_x: Option<f64>,
+    #[serde(rename = "anchorY")]
+    pub anchor_y: Option<f64>,
+    pub rotation: Option<f64>,
+    #[serde(rename = "cropLeft")]
+    pub crop_left: Option<f64>,
+    #[serde(rename = "cropRight")]
+    pub crop_right: Option<f64>,
+    #[serde(rename = "cropTop")]
+    pub crop_top: Option<f64>,
+    #[serde(rename = "cropBottom")]
+    pub crop_bottom: Option<f64>,
+}
+
+// Grading of a visual clip, applied to the source before it is placed on the canvas.
+// `exposure` is in stops, `temperature` and `tint` go from -1 (cool / green) to 1
+// (warm / magenta), `lift`/`gamma`/`gain` are the master wheels (0, 1 and 1 are neutral).
+// `lut` is a .cube file, relative to the project folder. Exposure, contrast, saturation,
+// temperature and tint can be animated with the track of the same name in Keyframes.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ColorCorrection {
+    pub exposure: Option<f64>,
+    pub contrast: Option<f64>,
+    pub saturation: Option<f64>,
+    pub temperature: Option<f64>,
+    pub tint: Option<f64>,
+    pub lift: Option<f64>,
+    pub gamma: Option<f64>,
+    pub gain: Option<f64>,
+    pub lut: Option<String>,
+}
+
+// Color key of a visual clip. Pixels within `similarity` (0.01-1) of `color` become
+// transparent, `blend` softens the edge and `spill` (0-1) removes the key color reflected
+// on the subject.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChromaKey {
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    pub color: String,
+    pub similarity: Option<f64>,
+    pub blend: Option<f64>,
+    pub spill: Option<f64>,
+}
+
+// One entry of a clip's audio effect chain, rendered in order after the trim. Levels are
+// in dB, times in milliseconds and frequencies in Hz.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AudioEffect {
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    #[serde(flatten)]
+    pub kind: AudioEffectKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AudioEffectKind {
+    Eq {
+        #[serde(default)]
+        bands: Vec<EqBand>,
+    },
+    Compressor {
+        threshold: Option<f64>,
+        ratio: Option<f64>,
+        attack: Option<f64>,
+        release: Option<f64>,
+        makeup: Option<f64>,
+        knee: Option<f64>,
+    },
+    Gate {
+        threshold: Option<f64>,
+        ratio: Option<f64>,
+        attack: Option<f64>,
+        release: Option<f64>,
+        range: Option<f64>,
+    },
+    Limiter {
+        ceiling: Option<f64>,
+        attack: Option<f64>,
+        release: Option<f64>,
+    },
+    // ffmpeg's deesser parameters, all from 0 to 1: `keep` is the share of the original
+    // frequency content kept on the sibilants (not a frequency)
+    #[serde(alias = "deEsser", rename_all = "camelCase")]
+    Deesser {
+        intensity: Option<f64>,
+        max_reduction: Option<f64>,
+        keep: Option<f64>,
+    },
+}
+
+// Band of the parametric EQ. `kind` is peak, lowshelf, highshelf, lowpass or highpass.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EqBand {
+    #[serde(rename = "type", default)]
+    pub kind: Option<String>,
+    pub frequency: f64,
+    #[serde(default)]
+    pub gain: f64,
+    pub q: Option<f64>,
+}
+
+// Transition on the cut between two adjacent clips of the same track. `kind` is one of
+// XFADE_TRANSITIONS or "dip" (through `color`). It is centered on the cut, so each clip
+// needs half of `duration` of extra media (handles) past its edge.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Transition {
+    #[serde(default = "default_transition_kind")]
+    pub kind: String,
+    pub duration: f64,
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+fn default_transition_kind() -> String {
+    "fade".to_string()
+}
+
+// Text of a `text` clip, drawn with drawtext on a transparent canvas the size of the project.
+// `align` places the block horizontally (left, center, right) and `verticalAlign` vertically
+// (top, middle, bottom), `margin` pixels away from the edges. Colors are "#RRGGBB[AA]" or names.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TextStyle {
+    #[serde(default)]
+    pub content: String,
+    pub font_file: Option<String>,
+    pub font_size: Option<f64>,
+    pub color: Option<String>,
+    pub line_spacing: Option<f64>,
+    pub outline_color: Option<String>,
+    pub outline_width: Option<f64>,
+    pub shadow_color: Option<String>,
+    pub shadow_x: Option<f64>,
+    pub shadow_y: Option<f64>,
+    pub box_color: Option<String>,
+    pub box_padding: Option<f64>,
+    pub align: Option<String>,
+    pub vertical_align: Option<String>,
+    pub margin: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -105,14 +512,219 @@ pub struct Clip {
     
     // Opcional: para suportar a nova estrutura de keyframes
     pub keyframes: Option<Keyframes>,
+
+    pub transform: Option<Transform>,
+
+    // Size of the asset, used to place the anchor of transformed clips
+    #[serde(rename = "dimentions", alias = "dimensions", default)]
+    pub dimensions: Option<Dimensions>,
+
+    // Transition from the previous clip of the same track into this one
+    #[serde(rename = "transitionIn", default)]
+    pub transition_in: Option<Transition>,
+
+    // Content and style of `text` clips
+    #[serde(default)]
+    pub text: Option<TextStyle>,
+
+    #[serde(rename = "colorCorrection", default)]
+    pub color_correction: Option<ColorCorrection>,
+
+    #[serde(rename = "chromaKey", default)]
+    pub chroma_key: Option<ChromaKey>,
+
+    // How the clip is combined with the layers below, see blend_mode_filter
+    #[serde(default)]
+    pub blendmode: Option<String>,
+
+    #[serde(rename = "audioEffects", default)]
+    pub audio_effects: Option<Vec<AudioEffect>>,
+
+    // Animated GIF/WebP repeat to fill the clip (default) or hold their last frame
+    #[serde(rename = "loop", default)]
+    pub looping: Option<bool>,
+
+    // Audio stream of the file (0 = first audio stream) and the channels of it to use,
+    // see build_channel_selection
+    #[serde(rename = "audioStream", default)]
+    pub audio_stream: Option<u32>,
+    #[serde(rename = "audioChannels", default)]
+    pub audio_channels: Option<Vec<u32>>,
     
     #[serde(rename = "activeKeyframeView")]
     pub active_keyframe_view: Option<String>,
+
+    // Set by clip_range when it cuts into a fade
+    #[serde(skip)]
+    pub fade_cut: FadeCut,
+}
+
+// Seconds of the fades cut off with the clip: of the fade in before the (new) start and
+// of the fade out after the end. The fades keep their duration and run from/to there, so
+// the part left has the same curve as in the full timeline.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FadeCut {
+    pub video_in: f64,
+    pub video_out: f64,
+    pub audio_in: f64,
+    pub audio_out: f64,
 }
 
 use tauri::Emitter; // Adicione este import no topo
 
 
+// Track as saved by the timeline. Layers are stacked by `zIndex` (higher on top); tracks
+// without it keep their order in the list, the first track being the top one.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Track {
+    #[serde(deserialize_with = "deserialize_track_id")]
+    pub id: String,
+    #[serde(rename = "type", default)]
+    pub track_type: Option<String>,
+    #[serde(rename = "zIndex", default)]
+    pub z_index: Option<i32>,
+    #[serde(default)]
+    pub mute: Option<bool>,
+    #[serde(default)]
+    pub solo: Option<bool>,
+    #[serde(default)]
+    pub hidden: Option<bool>,
+    // "dialogue", "music" or "effects"
+    #[serde(default)]
+    pub role: Option<String>,
+    // Sidechain ducking of a music track under the dialogue tracks
+    #[serde(default)]
+    pub ducking: Option<Ducking>,
+}
+
+// Compressor that lowers a music track while the dialogue tracks are over `threshold` (dB).
+// `attack` and `release` are in milliseconds.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Ducking {
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    pub threshold: Option<f64>,
+    pub ratio: Option<f64>,
+    pub attack: Option<f64>,
+    pub release: Option<f64>,
+}
+
+// Volume keyframes computed by bake_ducking_keyframes for one music clip
+#[derive(Debug, Serialize, Clone)]
+pub struct BakedVolume {
+    #[serde(rename = "clipId")]
+    pub clip_id: String,
+    pub keyframes: Vec<Keyframe>,
+}
+
+// The timeline uses numeric track ids while clips carry them as strings
+fn deserialize_track_id<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(id) => Ok(id),
+        other => Ok(other.to_string()),
+    }
+}
+
+
+// Output format of an export. `videoCodec` is h264, h265, vp9, av1, prores or dnxhr and
+// `container` mp4, mov, mkv or webm. Delivery codecs use `crf`, or `videoBitrate` (kbit/s)
+// when `rateControl` is "bitrate"; ProRes and DNxHR only take their `profile`.
+// `audioCodec` is aac, opus or pcm. Saved by name in export_presets.json.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportPreset {
+    pub name: String,
+    #[serde(default = "default_container")]
+    pub container: String,
+    #[serde(default = "default_video_codec")]
+    pub video_codec: String,
+    #[serde(default)]
+    pub rate_control: Option<String>,
+    pub crf: Option<u32>,
+    pub video_bitrate: Option<u32>,
+    // Encoder speed (x264/x265 names, or the svt-av1 number)
+    pub speed: Option<String>,
+    pub profile: Option<String>,
+    pub pixel_format: Option<String>,
+    #[serde(default = "default_audio_codec")]
+    pub audio_codec: String,
+    pub audio_bitrate: Option<u32>,
+}
+
+fn default_container() -> String {
+    "mp4".to_string()
+}
+
+fn default_video_codec() -> String {
+    "h264".to_string()
+}
+
+fn default_audio_codec() -> String {
+    "aac".to_string()
+}
+
+impl Default for ExportPreset {
+    fn default() -> Self {
+        ExportPreset {
+            name: "H.264 MP4".to_string(),
+            container: default_container(),
+            video_codec: default_video_codec(),
+            rate_control: None,
+            crf: Some(18),
+            video_bitrate: None,
+            speed: Some("medium".to_string()),
+            profile: None,
+            pixel_format: None,
+            audio_codec: default_audio_codec(),
+            audio_bitrate: Some(192),
+        }
+    }
+}
+
+// Part of the timeline to export, in timeline seconds
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportRange {
+    #[serde(rename = "in")]
+    pub in_point: f64,
+    #[serde(rename = "out")]
+    pub out_point: f64,
+}
+
+// Loudness the export is normalized to (EBU R128, measured by loudnorm), e.g. -14 LUFS and
+// -1 dBTP for streaming or -23 LUFS for broadcast
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LoudnessTarget {
+    #[serde(default = "default_integrated_loudness")]
+    pub integrated: f64,
+    #[serde(default = "default_true_peak")]
+    pub true_peak: f64,
+    #[serde(default = "default_loudness_range")]
+    pub lra: f64,
+}
+
+fn default_integrated_loudness() -> f64 {
+    -14.0
+}
+
+fn default_true_peak() -> f64 {
+    -1.0
+}
+
+fn default_loudness_range() -> f64 {
+    11.0
+}
+
+// Values printed by the measurement pass of loudnorm, sent to the UI as `export-loudness`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoudnessMeasurement {
+    pub input_i: String,
+    pub input_tp: String,
+    pub input_lra: String,
+    pub input_thresh: String,
+    pub target_offset: String,
+}
+
 #[derive(Serialize)]
 struct ExportPayload {
     export_path: String,
@@ -123,7 +735,7 @@ struct ExportPayload {
 
 use tauri::{ Runtime}; // Certifique-se de usar Emitter no Tauri v2
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProjectSettings {
     name: String,
     width: u32,
@@ -136,46 +748,12 @@ pub struct ProjectSettings {
 }
 
 
+// Width and height of an image or video, from probe_media
 #[tauri::command]
-async fn get_asset_dimensions(path: String) -> Result<Dimensions, String> {
-    let path_obj = Path::new(&path);
-    
-    let extension = path_obj.extension()
-        .and_then(|s| s.to_str())
-        .map(|s| s.to_lowercase())
-        .ok_or("Arquivo sem extensão válida")?;
-
-    // --- LÓGICA PARA IMAGENS ---
-    if ["jpg", "jpeg", "png", "webp", "bmp"].contains(&extension.as_str()) {
-        let img = image::image_dimensions(&path)
-            .map_err(|e| format!("Erro ao ler imagem: {}", e))?;
-        return Ok(Dimensions { x: img.0 as f64, y: img.1 as f64 });
-    }
-
-    // --- LÓGICA PARA VÍDEOS (Via OpenCV) ---
-    // OpenCV abre o arquivo e lê o cabeçalho via FFmpeg interno do sistema
-    let mut v_cap = videoio::VideoCapture::from_file(&path, videoio::CAP_ANY)
-        .map_err(|e| format!("OpenCV não conseguiu abrir o vídeo: {}", e))?;
-
-    let opened = videoio::VideoCapture::is_opened(&v_cap)
-        .map_err(|e| e.to_string())?;
-
-    if !opened {
-        return Err("Falha ao abrir stream de vídeo".to_string());
-    }
-
-    // CAP_PROP_FRAME_WIDTH e HEIGHT retornam as dimensões reais do vídeo
-    let width = v_cap.get(videoio::CAP_PROP_FRAME_WIDTH).map_err(|e| e.to_string())?;
-    let height = v_cap.get(videoio::CAP_PROP_FRAME_HEIGHT).map_err(|e| e.to_string())?;
-
-    if width == 0.0 || height == 0.0 {
-        return Err("Não foi possível determinar as dimensões do vídeo".to_string());
-    }
-
-    Ok(Dimensions {
-        x: width,
-        y: height,
-    })
+async fn get_asset_dimensions(app_handle: tauri::AppHandle, path: String, project_path: Option<String>) -> Result<Dimensions, String> {
+    let info = probe_media_with_cache(&app_handle, project_path.as_deref().map(Path::new), &path)?;
+    info.display_dimensions()
+        .ok_or_else(|| "Não foi possível determinar as dimensões do arquivo".to_string())
 }
 
 #[tauri::command]
@@ -274,299 +852,2366 @@ async fn load_project_config(path: String) -> Result<ProjectSettings, String> {
 
 
 
-#[tauri::command]
-async fn export_video(
-    app_handle: tauri::AppHandle,
-    state: tauri::State<'_, ExportState>,
-    project_path: String,
-    export_path: String,
-    project_dimensions: serde_json::Value,
-    clips: serde_json::Value,
-) -> Result<(), String> {
-    
-    // 1. Criar o objeto de configuração
-    let config_data = serde_json::json!({
-        "project_path": project_path,
-        "export_path": export_path,
-        "project_dimensions": project_dimensions,
-        "clips": clips
-    });
+// Reads projectConfig.json from the project folder
+fn read_project_settings(project_dir: &Path) -> Result<ProjectSettings, String> {
+    let content = fs::read_to_string(project_dir.join("projectConfig.json"))
+        .map_err(|e| format!("Erro ao ler projectConfig.json: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("JSON Inválido ou campos faltando: {}", e))
+}
 
-    // 2. Definir o caminho do JSON dentro da pasta do projeto
-    let project_dir = std::path::PathBuf::from(&project_path);
-    
-    // Garante que a pasta do projeto existe
-    if !project_dir.exists() {
-        return Err(format!("A pasta do projeto não existe: {}", project_path));
-    }
+// Timeline length covered by the clips
+fn timeline_duration(clips: &[Clip]) -> f64 {
+    clips.iter().map(|c| c.start + c.duration).fold(0.0, f64::max)
+}
 
-    let config_path = project_dir.join("export_config.json");
+fn is_image_clip(clip: &Clip) -> bool {
+    let path_lower = clip.path.to_lowercase();
+    path_lower.ends_with(".png") || path_lower.ends_with(".jpg") || path_lower.ends_with(".jpeg") || path_lower.ends_with(".webp")
+}
 
-    // 3. Salvar/Sobrescrever o JSON
-    let json_string = serde_json::to_string_pretty(&config_data)
-        .map_err(|e| format!("Erro ao serializar JSON: {}", e))?;
-    
-    std::fs::write(&config_path, json_string)
-        .map_err(|e| format!("Erro ao gravar export_config.json no projeto: {}", e))?;
+// GIFs are read by ffmpeg directly; animated WebP reach the graph as the frames that
+// prepare_export extracts, listed in an .ffconcat file (still WebP stay images)
+fn is_animated_clip(clip: &Clip) -> bool {
+    let path_lower = clip.path.to_lowercase();
+    path_lower.ends_with(".gif") || path_lower.ends_with(".ffconcat")
+}
 
-    let config_path_str = config_path.to_string_lossy().to_string();
+fn is_text_clip(clip: &Clip) -> bool {
+    clip.clip_type == "text"
+}
 
-    // 4. Iniciar o Sidecar Python
-    let (mut rx, child) = app_handle
-        .shell()
-        .sidecar("exporter")
-        .map_err(|e| format!("Sidecar não encontrado: {}", e))?
-        .env("PYTHONUNBUFFERED", "1")
-        .arg(&config_path_str) // Passamos o caminho completo do JSON dentro do projeto
-        .spawn()
-        .map_err(|e| format!("Falha ao iniciar processo Python: {}", e))?;
+fn is_image_sequence_clip(clip: &Clip) -> bool {
+    clip.path.to_lowercase().ends_with(IMAGE_SEQUENCE_EXTENSION)
+}
 
-    // Guardar processo para cancelamento
-    {
-        let mut lock = state.0.lock().unwrap();
-        *lock = Some(child);
-    }
-
-    // 5. Monitorização do progresso (Stderr)
-    tauri::async_runtime::spawn(async move {
-        while let Some(event) = rx.recv().await {
-            match event {
-                tauri_plugin_shell::process::CommandEvent::Stderr(line_bytes) => {
-                    let raw = String::from_utf8_lossy(&line_bytes);
-                    for line in raw.lines() {
-                        let line = line.trim();
-                        
-                        if line.contains("PERCENT:") {
-                            if let Some(val_str) = line.split("PERCENT:").last() {
-                                if let Ok(percent) = val_str.parse::<u32>() {
-                                    // Log para você acompanhar no terminal
-                                    println!("Progresso Real: {}%", percent);
-                                    let _ = app_handle.emit("export-progress", percent);
-                                }
-                            }
-                        }
-                        // println!("[Python Log]: {}", line); // Opcional
-                    }
-                }
-                tauri_plugin_shell::process::CommandEvent::Terminated(status) => {
-                    println!("Renderização concluída com código: {:?}", status.code);
-                    // Opcional: manter o json no projeto para histórico ou apagar
-                    // let _ = std::fs::remove_file(config_path);
-                    break;
-                }
-                _ => {}
+fn is_audio_clip(clip: &Clip) -> bool {
+    let path_lower = clip.path.to_lowercase();
+    clip.clip_type == "audio" || path_lower.ends_with(".mp3") || path_lower.ends_with(".wav")
+}
+
+// Global options and one input per clip. Input `i` is clip `i`, matching the
+// `[i:v]`/`[i:a]` labels used by build_rendering_filter.
+fn build_input_args(clips: &[Clip], settings: &ProjectSettings) -> Vec<String> {
+    let mut args: Vec<String> = vec![
+        "-y".into(), "-hide_banner".into(), "-nostats".into(),
+        "-progress".into(), "pipe:1".into(),
+    ];
+    let plan = plan_transitions(clips);
+
+    for (clip, edges) in clips.iter().zip(&plan) {
+        if is_text_clip(clip) {
+            // Text is drawn in the graph over a transparent canvas as long as the clip
+            args.extend([
+                "-f".into(), "lavfi".into(),
+                "-i".into(), format!(
+                    "color=c=black@0:s={}x{}:r={}:d={:.4}",
+                    settings.width, settings.height, settings.fps, clip.duration
+                ),
+            ]);
+            continue;
+        }
+        if let Some(sequence_args) = image_sequence_input_args(Path::new(&clip.path)) {
+            args.extend(sequence_args);
+            continue;
+        }
+        if is_animated_clip(clip) {
+            if clip.path.to_lowercase().ends_with(".ffconcat") {
+                args.extend(["-f".into(), "concat".into(), "-safe".into(), "0".into()]);
+            }
+            // Looped animations are endless, read only what the clip and its transition handle
+            // after the out point use (the one before the in point is inside beginmoment).
+            // Without loop the input just ends: the last frame is held by the overlay of the
+            // layer (eof_action=repeat, its default), not here
+            if clip.looping.unwrap_or(true) {
+                let (_, post_src) = source_handles(clip, edges);
+                args.extend([
+                    "-stream_loop".into(), "-1".into(),
+                    "-t".into(), format!("{:.4}", clip.beginmoment.max(0.0) + clip_source_duration(clip) + post_src),
+                ]);
             }
         }
-    });
+        if is_image_clip(clip) {
+            // Stills become a stream as long as the clip
+            args.extend([
+                "-loop".into(), "1".into(),
+                "-framerate".into(), settings.fps.to_string(),
+                "-t".into(), format!("{:.4}", clip.duration),
+            ]);
+        }
+        args.extend(["-i".into(), clip.path.clone()]);
+    }
 
-    Ok(())
+    args
 }
 
+// Full ffmpeg command line for an export. The graph itself is read from `graph_path`
+// because it easily exceeds the maximum length of a single argument.
+fn build_export_args(
+    clips: &[Clip],
+    settings: &ProjectSettings,
+    preset: &ExportPreset,
+    total_duration: f64,
+    graph_path: &Path,
+    export_path: &str,
+) -> Vec<String> {
+    let mut args = build_input_args(clips, settings);
+
+    args.extend([
+        "-filter_complex_script".into(), graph_path.to_string_lossy().into_owned(),
+        "-map".into(), "[outv]".into(),
+        "-map".into(), "[outa]".into(),
+    ]);
+    args.extend(build_video_codec_args(preset));
+    args.extend(["-r".into(), settings.fps.to_string()]);
+    args.extend(build_audio_codec_args(preset));
+    args.extend([
+        "-ar".into(), settings.sample_rate.to_string(),
+        "-t".into(), format!("{:.4}", total_duration),
+    ]);
+    if matches!(preset.container.as_str(), "mp4" | "mov") {
+        args.extend(["-movflags".into(), "+faststart".into()]);
+    }
+    args.extend([
+        "-f".into(), container_format(&preset.container).to_string(),
+        export_path.to_string(),
+    ]);
 
-pub fn build_rendering_filter(clips: &[Clip], total_duration: f64) -> String {
-    let mut filters = Vec::new();
-    let mut audio_outputs = Vec::new();
-    let mut video_layers = Vec::new();
+    args
+}
 
-    let centering_filter = "scale=1920:1080:force_original_aspect_ratio=decrease,pad=1920:1080:(ow-iw)/2:(oh-ih)/2";
+fn container_format(container: &str) -> &'static str {
+    match container {
+        "mov" => "mov",
+        "mkv" => "matroska",
+        "webm" => "webm",
+        _ => "mp4",
+    }
+}
 
-    for (i, clip) in clips.iter().enumerate() {
-        let path_lower = clip.path.to_lowercase();
-        let is_image = path_lower.ends_with(".png") || path_lower.ends_with(".jpg") || path_lower.ends_with(".jpeg");
-        let is_audio = clip.clip_type == "audio" || path_lower.ends_with(".mp3") || path_lower.ends_with(".wav");
+// Rejects the codec/container pairs the muxers do not accept
+fn validate_export_preset(preset: &ExportPreset) -> Result<(), String> {
+    let video = preset.video_codec.as_str();
+    let audio = preset.audio_codec.as_str();
+    if !["h264", "h265", "vp9", "av1", "prores", "dnxhr"].contains(&video) {
+        return Err(format!("Codec de vídeo desconhecido: {}", video));
+    }
+    if !["aac", "opus", "pcm"].contains(&audio) {
+        return Err(format!("Codec de áudio desconhecido: {}", audio));
+    }
+    let compatible = match preset.container.as_str() {
+        "mp4" => !matches!(video, "prores" | "dnxhr") && audio != "pcm",
+        "mov" => video != "vp9" && audio != "opus",
+        "mkv" => true,
+        "webm" => matches!(video, "vp9" | "av1") && audio == "opus",
+        other => return Err(format!("Container desconhecido: {}", other)),
+    };
+    if !compatible {
+        return Err(format!("{} com {} não é suportado em {}", video, audio, preset.container));
+    }
+    Ok(())
+}
 
-        // --- Processamento de Vídeo/Imagem ---
-        if !is_audio {
-            let mut v_filters = Vec::new();
+// Pixel format of the output, the preset's or the usual one of its codec. The filter graph
+// ends in it too, so alpha and 10 bit survive up to the encoder.
+fn output_pixel_format(preset: &ExportPreset) -> String {
+    if let Some(format) = &preset.pixel_format {
+        return format.clone();
+    }
+    match preset.video_codec.as_str() {
+        "prores" => match preset.profile.as_deref() {
+            Some("4") | Some("5") => "yuva444p10le",
+            _ => "yuv422p10le",
+        },
+        "dnxhr" => match preset.profile.as_deref() {
+            Some("dnxhr_444") => "yuv444p10le",
+            Some("dnxhr_hqx") => "yuv422p10le",
+            _ => "yuv422p",
+        },
+        _ => "yuv420p",
+    }
+    .to_string()
+}
 
-        //check if has keyframes, if result 1 there is no keyframes
+fn build_video_codec_args(preset: &ExportPreset) -> Vec<String> {
+    let by_bitrate = preset.rate_control.as_deref() == Some("bitrate") && preset.video_bitrate.is_some();
+    let rate = |crf_default: u32| -> Vec<String> {
+        match preset.video_bitrate.filter(|_| by_bitrate) {
+            Some(kbps) => vec!["-b:v".into(), format!("{}k", kbps)],
+            None => vec!["-crf".into(), preset.crf.unwrap_or(crf_default).to_string()],
+        }
+    };
+
+    let mut args: Vec<String> = Vec::new();
+    match preset.video_codec.as_str() {
+        "h265" => {
+            args.extend(["-c:v".into(), "libx265".into()]);
+            args.extend(["-preset".into(), preset.speed.clone().unwrap_or_else(|| "medium".into())]);
+            args.extend(rate(22));
+            // QuickTime only plays HEVC tagged as hvc1
+            args.extend(["-tag:v".into(), "hvc1".into()]);
+        }
+        "vp9" => {
+            args.extend(["-c:v".into(), "libvpx-vp9".into(), "-row-mt".into(), "1".into()]);
+            args.extend(rate(31));
+            if !by_bitrate {
+                // Constant quality in libvpx needs the bitrate limit off
+                args.extend(["-b:v".into(), "0".into()]);
+            }
+        }
+        "av1" => {
+            args.extend(["-c:v".into(), "libsvtav1".into()]);
+            args.extend(["-preset".into(), preset.speed.clone().unwrap_or_else(|| "8".into())]);
+            args.extend(rate(30));
+        }
+        "prores" => {
+            // 0 proxy, 1 lt, 2 standard, 3 hq, 4 4444, 5 4444xq
+            let profile = preset.profile.clone().unwrap_or_else(|| "3".into());
+            args.extend(["-c:v".into(), "prores_ks".into(), "-profile:v".into(), profile]);
+            args.extend(["-vendor".into(), "apl0".into()]);
+        }
+        "dnxhr" => {
+            let profile = preset.profile.clone().unwrap_or_else(|| "dnxhr_hq".into());
+            args.extend(["-c:v".into(), "dnxhd".into(), "-profile:v".into(), profile]);
+        }
+        _ => {
+            args.extend(["-c:v".into(), "libx264".into()]);
+            args.extend(["-preset".into(), preset.speed.clone().unwrap_or_else(|| "medium".into())]);
+            args.extend(rate(18));
+        }
+    }
+    args.extend(["-pix_fmt".into(), output_pixel_format(preset)]);
+    args
+}
 
-        let opacity_kfs = clip.keyframes.as_ref()
-                .and_then(|k| k.opacity.as_ref())
-                .map(|v| v.as_slice())
-                .unwrap_or(&[]);
+fn build_audio_codec_args(preset: &ExportPreset) -> Vec<String> {
+    match preset.audio_codec.as_str() {
+        "opus" => vec![
+            "-c:a".into(), "libopus".into(),
+            "-b:a".into(), format!("{}k", preset.audio_bitrate.unwrap_or(160)),
+        ],
+        "pcm" => vec!["-c:a".into(), "pcm_s24le".into()],
+        _ => vec![
+            "-c:a".into(), "aac".into(),
+            "-b:a".into(), format!("{}k", preset.audio_bitrate.unwrap_or(192)),
+        ],
+    }
+}
 
-        let opacity_expression = build_opacity_expression(opacity_kfs);
+// Presets offered even when export_presets.json does not exist yet
+fn builtin_export_presets() -> Vec<ExportPreset> {
+    let base = ExportPreset::default();
+    vec![
+        base.clone(),
+        ExportPreset {
+            name: "H.265 MP4".into(), video_codec: "h265".into(), crf: Some(22), ..base.clone()
+        },
+        ExportPreset {
+            name: "VP9 WebM".into(), container: "webm".into(), video_codec: "vp9".into(),
+            crf: Some(31), speed: None, audio_codec: "opus".into(), audio_bitrate: Some(160), ..base.clone()
+        },
+        ExportPreset {
+            name: "AV1 MKV".into(), container: "mkv".into(), video_codec: "av1".into(),
+            crf: Some(30), speed: Some("8".into()), audio_codec: "opus".into(), audio_bitrate: Some(160), ..base.clone()
+        },
+        ExportPreset {
+            name: "ProRes 422 HQ".into(), container: "mov".into(), video_codec: "prores".into(),
+            crf: None, speed: None, profile: Some("3".into()), audio_codec: "pcm".into(), audio_bitrate: None, ..base.clone()
+        },
+        ExportPreset {
+            name: "DNxHR HQ".into(), container: "mov".into(), video_codec: "dnxhr".into(),
+            crf: None, speed: None, profile: Some("dnxhr_hq".into()), audio_codec: "pcm".into(), audio_bitrate: None, ..base
+        },
+    ]
+}
 
-        if opacity_expression == "1" 
-        {
-            
-            if is_image {
+fn export_presets_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app_handle.path().app_config_dir().map_err(|e| format!("Pasta de configuração indisponível: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Erro ao criar a pasta de configuração: {}", e))?;
+    Ok(dir.join("export_presets.json"))
+}
 
-                                
-                    v_filters.push("format=yuva420p".to_string());
+// Saved presets, or the built-in ones when nothing was saved yet
+fn read_export_presets(app_handle: &tauri::AppHandle) -> Result<Vec<ExportPreset>, String> {
+    let path = export_presets_path(app_handle)?;
+    if !path.exists() {
+        return Ok(builtin_export_presets());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("Erro ao ler export_presets.json: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("export_presets.json inválido: {}", e))
+}
 
-                    if let Some(fi) = clip.fadein {
-                        if fi > 0.0 { 
-                            v_filters.push(format!("fade=t=in:st=0:d={:.4}:alpha=1", fi)); 
-                        }
-                    }
-                    if let Some(fo) = clip.fadeout {
-                        if fo > 0.0 {
-                            let st = clip.duration - fo;
-                            v_filters.push(format!("fade=t=out:st={:.4}:d={:.4}:alpha=1", st, fo));
-                        }
-                    }
+fn write_export_presets(app_handle: &tauri::AppHandle, presets: &[ExportPreset]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(presets).map_err(|e| format!("Erro ao gerar JSON: {}", e))?;
+    fs::write(export_presets_path(app_handle)?, json).map_err(|e| format!("Erro ao gravar export_presets.json: {}", e))
+}
 
-                    let v_effects = if v_filters.is_empty() { "".to_string() } else { format!(",{}", v_filters.join(",")) };
+#[tauri::command]
+fn list_export_presets(app_handle: tauri::AppHandle) -> Result<Vec<ExportPreset>, String> {
+    read_export_presets(&app_handle)
+}
+
+// Adds the preset or replaces the one with the same name
+#[tauri::command]
+fn save_export_preset(app_handle: tauri::AppHandle, preset: ExportPreset) -> Result<Vec<ExportPreset>, String> {
+    validate_export_preset(&preset)?;
+    let mut presets = read_export_presets(&app_handle)?;
+    match presets.iter_mut().find(|p| p.name == preset.name) {
+        Some(existing) => *existing = preset,
+        None => presets.push(preset),
+    }
+    write_export_presets(&app_handle, &presets)?;
+    Ok(presets)
+}
+
+#[tauri::command]
+fn delete_export_preset(app_handle: tauri::AppHandle, name: String) -> Result<Vec<ExportPreset>, String> {
+    let mut presets = read_export_presets(&app_handle)?;
+    presets.retain(|p| p.name != name);
+    write_export_presets(&app_handle, &presets)?;
+    Ok(presets)
+}
+
+// Analysis passes (loudness measurement, ducking levels): only `[outa]` of the graph is
+// rendered and nothing is written, the filters at its end report what they measured.
+fn build_audio_analysis_args(
+    clips: &[Clip],
+    settings: &ProjectSettings,
+    total_duration: f64,
+    graph_path: &Path,
+) -> Vec<String> {
+    let mut args = build_input_args(clips, settings);
+
+    args.extend([
+        "-filter_complex_script".into(), graph_path.to_string_lossy().into_owned(),
+        "-map".into(), "[outa]".into(),
+        "-t".into(), format!("{:.4}", total_duration),
+        "-f".into(), "null".into(),
+        "-".into(),
+    ]);
+
+    args
+}
+
+// Reads the `out_time_us` key of ffmpeg's -progress output as a percentage of the timeline
+fn parse_progress_line(line: &str, total_duration: f64) -> Option<u32> {
+    let value = line.trim().strip_prefix("out_time_us=")?;
+    let micros = value.parse::<f64>().ok()?;
+    if total_duration <= 0.0 {
+        return None;
+    }
+    let percent = (micros / 1_000_000.0 / total_duration * 100.0).clamp(0.0, 99.0);
+    Some(percent as u32)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportJobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+// One export of the queue, with everything export_video received so it can be run (again,
+// after a restart) later. Pending jobs are saved in export_queue.json in the project.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportJob {
+    pub id: String,
+    pub status: ExportJobStatus,
+    #[serde(default)]
+    pub progress: u32,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub project_path: String,
+    pub export_path: String,
+    pub project_dimensions: serde_json::Value,
+    pub clips: serde_json::Value,
+    #[serde(default)]
+    pub tracks: Option<serde_json::Value>,
+    #[serde(default)]
+    pub loudness: Option<LoudnessTarget>,
+    #[serde(default)]
+    pub preset: Option<ExportPreset>,
+    #[serde(default)]
+    pub range: Option<ExportRange>,
+}
+
+// Payload of the `export-job` event, sent on every status or progress change
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportJobUpdate {
+    pub id: String,
+    pub status: ExportJobStatus,
+    pub progress: u32,
+    pub error: Option<String>,
+}
+
+impl ExportJob {
+    fn update(&self) -> ExportJobUpdate {
+        ExportJobUpdate { id: self.id.clone(), status: self.status, progress: self.progress, error: self.error.clone() }
+    }
+}
+
+// Everything a job needs to run its ffmpeg passes
+pub struct PreparedExport {
+    settings: ProjectSettings,
+    total_duration: f64,
+    graph: String,
+    graph_path: PathBuf,
+    args: Vec<String>,
+    measure_args: Option<Vec<String>>,
+}
+
+// Builds the graphs of a job and writes them to the project (one file per job, since jobs
+// of the same project may run together). `output_scale` shrinks the rendered picture, the
+// graph itself stays at the project size so positions and text match the export.
+fn prepare_export(app_handle: &tauri::AppHandle, job: &ExportJob, output_scale: Option<f64>) -> Result<PreparedExport, String> {
+    let project_dir = std::path::PathBuf::from(&job.project_path);
+    
+    // Garante que a pasta do projeto existe
+    if !project_dir.exists() {
+        return Err(format!("A pasta do projeto não existe: {}", job.project_path));
+    }
+
+    // 1. Clips vindos da timeline
+    let clips: Vec<Clip> = serde_json::from_value(job.clips.clone())
+        .map_err(|e| format!("Clips inválidos: {}", e))?;
+
+    if clips.is_empty() {
+        return Err("Nada para exportar: a timeline está vazia".into());
+    }
+
+    let tracks: Vec<Track> = match &job.tracks {
+        Some(value) => serde_json::from_value(value.clone()).map_err(|e| format!("Tracks inválidas: {}", e))?,
+        None => Vec::new(),
+    };
+
+    // LUTs are saved relative to the project, the graph needs their full path
+    let mut clips = clips;
+    for clip in clips.iter_mut() {
+        // Exports always read the original media, even if the UI handed a proxy
+        if let Some(original) = original_for_proxy(Path::new(&clip.path)) {
+            clip.path = original.to_string_lossy().into_owned();
+        }
+        // Transforms need the size of the asset, taken from the probe cache when the UI lacks it
+        if clip.dimensions.is_none() && !is_audio_clip(clip) && !is_text_clip(clip) {
+            clip.dimensions = probe_media_with_cache(app_handle, Some(&project_dir), &clip.path)
+                .ok()
+                .and_then(|info| info.display_dimensions());
+        }
+        if clip.audio_stream.is_some() || clip.audio_channels.is_some() {
+            let info = probe_media_with_cache(app_handle, Some(&project_dir), &clip.path)?;
+            validate_audio_selection(clip, &info)?;
+        }
+        // ffmpeg does not decode animated WebP, its frames are written out once
+        if clip.path.to_lowercase().ends_with(".webp") {
+            if let Some(list) = extract_animation_frames(&project_dir, Path::new(&clip.path))? {
+                clip.path = list.to_string_lossy().into_owned();
+            }
+        }
+        if let Some(lut) = clip.color_correction.as_mut().and_then(|c| c.lut.as_mut()) {
+            let lut_path = project_dir.join(lut.as_str());
+            if !lut_path.is_file() {
+                return Err(format!("LUT não encontrado: {}", lut_path.display()));
+            }
+            *lut = lut_path.to_string_lossy().into_owned();
+        }
+    }
+
+    // 2. Configurações do projeto (projectConfig.json). Sem elas o render sairia com o
+    // tamanho e o frame rate errados, então é um erro
+    let settings = read_project_settings(&project_dir)?;
+
+    // Only the in/out range: clips are cut to it and moved so it starts at zero
+    let (clips, total_duration) = match &job.range {
+        Some(range) => {
+            let out_point = range.out_point.min(timeline_duration(&clips));
+            let in_point = range.in_point.max(0.0);
+            if out_point - in_point <= 0.0 {
+                return Err("O intervalo de exportação está vazio".into());
+            }
+            (clip_range(&clips, in_point, out_point), out_point - in_point)
+        }
+        None => {
+            let total_duration = timeline_duration(&clips);
+            (clips, total_duration)
+        }
+    };
+
+    // Formato de saída, H.264/AAC em MP4 se a UI não mandar um preset
+    let preset = job.preset.clone().unwrap_or_default();
+    validate_export_preset(&preset)?;
+
+    // 3. Monta o grafo de filtros e grava no projeto
+    let output_size = output_scale.map(|scale| scaled_output_size(&settings, scale));
+    let graph = build_rendering_filter(&clips, &tracks, total_duration, &settings, &output_pixel_format(&preset), output_size);
+
+    let graph_path = project_dir.join(format!("export_filter_{}.txt", job.id));
+    std::fs::write(&graph_path, &graph)
+        .map_err(|e| format!("Erro ao gravar o grafo de exportação no projeto: {}", e))?;
+
+    let args = build_export_args(&clips, &settings, &preset, total_duration, &graph_path, &job.export_path);
+
+    // Loudness: the mix is measured first (audio only) and the render corrects it
+    let measure_args = match &job.loudness {
+        Some(target) => {
+            let audio_graph = build_filter_graph(&clips, &tracks, total_duration, &settings, None, None);
+            let measure_path = project_dir.join(format!("export_loudness_filter_{}.txt", job.id));
+            std::fs::write(&measure_path, append_loudnorm(&audio_graph, target, None, settings.sample_rate))
+                .map_err(|e| format!("Erro ao gravar o grafo de loudness no projeto: {}", e))?;
+            Some(build_audio_analysis_args(&clips, &settings, total_duration, &measure_path))
+        }
+        None => None,
+    };
+
+    Ok(PreparedExport { settings, total_duration, graph, graph_path, args, measure_args })
+}
+
+// Project size times `scale`, rounded to even sizes (yuv420p needs them)
+fn scaled_output_size(settings: &ProjectSettings, scale: f64) -> (u32, u32) {
+    let even = |size: u32| ((size as f64 * scale / 2.0).round() as u32).max(1) * 2;
+    (even(settings.width), even(settings.height))
+}
+
+// Adds an export to the queue and returns its job id. Progress and the end of the job
+// arrive as `export-job` events, a failure also as `export-error` with its message.
+#[tauri::command]
+async fn export_video(
+    app_handle: tauri::AppHandle,
+    project_path: String,
+    export_path: String,
+    project_dimensions: serde_json::Value,
+    clips: serde_json::Value,
+    tracks: Option<serde_json::Value>,
+    loudness: Option<LoudnessTarget>,
+    preset: Option<ExportPreset>,
+    range: Option<ExportRange>,
+) -> Result<String, String> {
+    let job = ExportJob {
+        id: next_export_job_id(),
+        status: ExportJobStatus::Queued,
+        progress: 0,
+        error: None,
+        project_path,
+        export_path,
+        project_dimensions,
+        clips,
+        tracks,
+        loudness,
+        preset,
+        range,
+    };
+
+    // Erros do projeto chegam à UI já aqui, não só quando o job começa
+    let prepared = prepare_export(&app_handle, &job, None)?;
+
+    // Confere o Sidecar FFmpeg antes de devolver o controle à UI
+    app_handle
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| format!("Sidecar não encontrado: {}", e))?;
+
+    let id = job.id.clone();
+    let project_path = job.project_path.clone();
+    let _ = app_handle.emit("export-job", job.update());
+    {
+        let state = app_handle.state::<ExportState>();
+        let mut queue = state.0.lock().unwrap();
+        queue.prepared.insert(id.clone(), prepared);
+        queue.jobs.push(job);
+    }
+
+    save_export_queue(&app_handle, &project_path);
+    pump_export_queue(&app_handle);
+    Ok(id)
+}
+
+fn next_export_job_id() -> String {
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!("{}-{}", millis, COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
+}
+
+// Applies `change` to a job and tells the UI. Returns the updated job.
+fn update_export_job(app_handle: &tauri::AppHandle, id: &str, change: impl FnOnce(&mut ExportJob)) -> Option<ExportJob> {
+    let job = {
+        let state = app_handle.state::<ExportState>();
+        let mut queue = state.0.lock().unwrap();
+        let job = queue.jobs.iter_mut().find(|j| j.id == id)?;
+        change(job);
+        job.clone()
+    };
+    let _ = app_handle.emit("export-job", job.update());
+    Some(job)
+}
+
+// Saves the pending jobs of a project to its export_queue.json
+fn save_export_queue(app_handle: &tauri::AppHandle, project_path: &str) {
+    let jobs: Vec<ExportJob> = {
+        let state = app_handle.state::<ExportState>();
+        let queue = state.0.lock().unwrap();
+        queue.jobs.iter().filter(|j| j.project_path == project_path).cloned().collect()
+    };
+
+    // The queue on disk only matters after a restart, the export goes on and the UI is told
+    if let Err(e) = write_export_queue(&Path::new(project_path).join("export_queue.json"), &jobs) {
+        let _ = app_handle.emit("export-queue-error", e);
+    }
+}
+
+// Writes the pending (queued or running) jobs of `jobs` to `path`
+fn write_export_queue(path: &Path, jobs: &[ExportJob]) -> Result<(), String> {
+    let pending: Vec<&ExportJob> = jobs.iter()
+        .filter(|j| matches!(j.status, ExportJobStatus::Queued | ExportJobStatus::Running))
+        .collect();
+    let json = serde_json::to_string_pretty(&pending).map_err(|e| format!("Erro ao gerar JSON: {}", e))?;
+    fs::write(path, json).map_err(|e| format!("Erro ao gravar export_queue.json: {}", e))
+}
+
+// Jobs saved by write_export_queue, queued again from the start (the ones that were running
+// when the app closed start over)
+fn read_export_queue(path: &Path) -> Result<Vec<ExportJob>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Erro ao ler export_queue.json: {}", e))?;
+    let saved: Vec<ExportJob> = serde_json::from_str(&content)
+        .map_err(|e| format!("export_queue.json inválido: {}", e))?;
+    Ok(saved
+        .into_iter()
+        .map(|mut job| {
+            job.status = ExportJobStatus::Queued;
+            job.progress = 0;
+            job.error = None;
+            job
+        })
+        .collect())
+}
+
+// Starts queued jobs while there are free slots
+fn pump_export_queue(app_handle: &tauri::AppHandle) {
+    let started: Vec<ExportJob> = {
+        let state = app_handle.state::<ExportState>();
+        let mut queue = state.0.lock().unwrap();
+        let mut running = queue.jobs.iter().filter(|j| j.status == ExportJobStatus::Running).count();
+        let concurrency = queue.concurrency;
+        let mut started = Vec::new();
+        for job in queue.jobs.iter_mut() {
+            if running >= concurrency {
+                break;
+            }
+            if job.status == ExportJobStatus::Queued {
+                job.status = ExportJobStatus::Running;
+                job.progress = 0;
+                running += 1;
+                started.push(job.clone());
+            }
+        }
+        started
+    };
+
+    for job in started {
+        let _ = app_handle.emit("export-job", job.update());
+        save_export_queue(app_handle, &job.project_path);
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            run_export_job(&app_handle, &job).await;
+        });
+    }
+}
+
+// Ends a job (unless it was cancelled meanwhile) and moves the queue on
+fn finish_export_job(app_handle: &tauri::AppHandle, id: &str, status: ExportJobStatus, error: Option<String>) {
+    let job = update_export_job(app_handle, id, |job| {
+        if job.status != ExportJobStatus::Cancelled {
+            job.status = status;
+            job.error = error.clone();
+            if status == ExportJobStatus::Done {
+                job.progress = 100;
+            }
+        }
+    });
+
+    if let Some(job) = job {
+        if job.status == ExportJobStatus::Failed {
+            let _ = app_handle.emit("export-error", job.error.clone().unwrap_or_default());
+        }
+        remove_export_graphs(&job);
+        save_export_queue(app_handle, &job.project_path);
+    }
+    pump_export_queue(app_handle);
+}
+
+// Deletes the graph files prepare_export wrote for a job that ended
+fn remove_export_graphs(job: &ExportJob) {
+    let project_dir = Path::new(&job.project_path);
+    for name in [format!("export_filter_{}.txt", job.id), format!("export_loudness_filter_{}.txt", job.id)] {
+        remove_work_file(&project_dir.join(name));
+    }
+}
+
+// Deletes a file written for one ffmpeg run, if it is there
+fn remove_work_file(path: &Path) {
+    if path.exists() {
+        if let Err(e) = fs::remove_file(path) {
+            eprintln!("Erro ao apagar {}: {}", path.display(), e);
+        }
+    }
+}
+
+// Passes of one job: the loudness measurement when asked for, then the render
+async fn run_export_job(app_handle: &tauri::AppHandle, job: &ExportJob) {
+    // Built by export_video, or again for jobs restored from export_queue.json
+    let prepared = app_handle.state::<ExportState>().0.lock().unwrap().prepared.remove(&job.id);
+    let prepared = match prepared.map(Ok).unwrap_or_else(|| prepare_export(app_handle, job, None)) {
+        Ok(prepared) => prepared,
+        Err(e) => return finish_export_job(app_handle, &job.id, ExportJobStatus::Failed, Some(e)),
+    };
+    let total_duration = prepared.total_duration;
+    let mut render_from = 0;
+
+    if let (Some(measure_args), Some(target)) = (prepared.measure_args, &job.loudness) {
+        let stderr = match run_export_pass(app_handle, &job.id, measure_args, total_duration, 0, LOUDNESS_PASS_PROGRESS).await {
+            ExportPassEnd::Finished(stderr) => stderr,
+            ExportPassEnd::Cancelled => return finish_export_job(app_handle, &job.id, ExportJobStatus::Cancelled, None),
+            ExportPassEnd::Failed(error) => return finish_export_job(app_handle, &job.id, ExportJobStatus::Failed, Some(error)),
+        };
+
+        let measured = match parse_loudnorm_output(&stderr) {
+            Some(measured) => measured,
+            None => {
+                let error = format!("Falha ao medir o loudness:\n{}", stderr);
+                return finish_export_job(app_handle, &job.id, ExportJobStatus::Failed, Some(error));
+            }
+        };
+        let _ = app_handle.emit("export-loudness", measured.clone());
+
+        // A silent timeline has no loudness to correct
+        if measured.input_i.parse::<f64>().map(|v| v.is_finite()).unwrap_or(false) {
+            let corrected = append_loudnorm(&prepared.graph, target, Some(&measured), prepared.settings.sample_rate);
+            if let Err(e) = std::fs::write(&prepared.graph_path, corrected) {
+                let error = format!("Erro ao gravar o grafo de exportação no projeto: {}", e);
+                return finish_export_job(app_handle, &job.id, ExportJobStatus::Failed, Some(error));
+            }
+        }
+        render_from = LOUDNESS_PASS_PROGRESS;
+    }
+
+    match run_export_pass(app_handle, &job.id, prepared.args, total_duration, render_from, 100).await {
+        ExportPassEnd::Finished(_) => finish_export_job(app_handle, &job.id, ExportJobStatus::Done, None),
+        ExportPassEnd::Cancelled => finish_export_job(app_handle, &job.id, ExportJobStatus::Cancelled, None),
+        ExportPassEnd::Failed(error) => finish_export_job(app_handle, &job.id, ExportJobStatus::Failed, Some(error)),
+    }
+}
+
+// Size of previews, as a fraction of the project size, when the UI does not pick one
+const PREVIEW_SCALE: f64 = 0.5;
+
+// Fast H.264 settings of the preview renders
+fn preview_preset() -> ExportPreset {
+    ExportPreset {
+        name: "Preview".to_string(),
+        crf: Some(28),
+        speed: Some("ultrafast".to_string()),
+        audio_bitrate: Some(128),
+        ..ExportPreset::default()
+    }
+}
+
+// Previews kept in preview_cache, the oldest ones are deleted after each render
+const PREVIEW_CACHE_SIZE: usize = 20;
+
+// Renders `range` of the timeline with the export graph at a reduced size into the
+// preview_cache folder of the project and returns the path of the file. A range whose
+// graph did not change is not rendered again. Loudness normalization is not applied.
+#[tauri::command]
+async fn render_preview(
+    app_handle: tauri::AppHandle,
+    project_path: String,
+    project_dimensions: serde_json::Value,
+    clips: serde_json::Value,
+    tracks: Option<serde_json::Value>,
+    range: ExportRange,
+    scale: Option<f64>,
+) -> Result<String, String> {
+    use std::hash::{Hash, Hasher};
+
+    let cache_dir = Path::new(&project_path).join("preview_cache");
+    fs::create_dir_all(&cache_dir).map_err(|e| format!("Erro ao criar preview_cache: {}", e))?;
+
+    // Each preview writes its own graph file, several may be rendering at once
+    let job = ExportJob {
+        id: format!("preview_{}", next_export_job_id()),
+        status: ExportJobStatus::Running,
+        progress: 0,
+        error: None,
+        project_path,
+        export_path: String::new(),
+        project_dimensions,
+        clips,
+        tracks,
+        loudness: None,
+        preset: Some(preview_preset()),
+        range: Some(range),
+    };
+    let scale = scale.unwrap_or(PREVIEW_SCALE).clamp(0.1, 1.0);
+    let mut prepared = prepare_export(&app_handle, &job, Some(scale))?;
+
+    // The graph and the inputs identify the render (not the graph file, named after the job)
+    let graph_arg = prepared.graph_path.to_string_lossy().into_owned();
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    prepared.graph.hash(&mut hasher);
+    prepared.args.iter().filter(|a| **a != graph_arg).for_each(|a| a.hash(&mut hasher));
+    let output = cache_dir.join(format!("preview_{:016x}.mp4", hasher.finish()));
+    if output.is_file() {
+        remove_export_graphs(&job);
+        return Ok(output.to_string_lossy().into_owned());
+    }
+
+    // The output is the last argument
+    if let Some(last) = prepared.args.last_mut() {
+        *last = output.to_string_lossy().into_owned();
+    }
+    let result = match app_handle.shell().sidecar("ffmpeg") {
+        Ok(command) => command.args(prepared.args).output().await.map_err(|e| format!("Falha ao iniciar o FFmpeg: {}", e)),
+        Err(e) => Err(format!("Sidecar não encontrado: {}", e)),
+    };
+    remove_export_graphs(&job);
+    let result = result?;
+    if !result.status.success() {
+        let _ = fs::remove_file(&output);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let lines: Vec<&str> = stderr.lines().collect();
+        return Err(lines[lines.len().saturating_sub(20)..].join("\n"));
+    }
+
+    prune_preview_cache(&cache_dir, PREVIEW_CACHE_SIZE);
+    Ok(output.to_string_lossy().into_owned())
+}
+
+// Keeps the `keep` most recently written previews of the folder
+fn prune_preview_cache(cache_dir: &Path, keep: usize) {
+    let mut previews: Vec<(std::time::SystemTime, PathBuf)> = match fs::read_dir(cache_dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|e| e == "mp4").unwrap_or(false))
+            .filter_map(|p| Some((fs::metadata(&p).and_then(|m| m.modified()).ok()?, p)))
+            .collect(),
+        Err(_) => return,
+    };
+    previews.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    for (_, path) in previews.into_iter().skip(keep) {
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("Erro ao apagar {}: {}", path.display(), e);
+        }
+    }
+}
+
+// Deletes every preview of the project, e.g. when the UI closes it
+#[tauri::command]
+fn clear_preview_cache(project_path: String) -> Result<(), String> {
+    let cache_dir = Path::new(&project_path).join("preview_cache");
+    if cache_dir.exists() {
+        fs::remove_dir_all(&cache_dir).map_err(|e| format!("Erro ao apagar preview_cache: {}", e))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn list_export_jobs(state: State<'_, ExportState>) -> Vec<ExportJob> {
+    state.0.lock().unwrap().jobs.clone()
+}
+
+#[tauri::command]
+fn set_export_concurrency(app_handle: tauri::AppHandle, concurrency: usize) {
+    app_handle.state::<ExportState>().0.lock().unwrap().concurrency = concurrency.clamp(1, 8);
+    pump_export_queue(&app_handle);
+}
+
+// Drops a finished, failed or cancelled job from the list
+#[tauri::command]
+fn remove_export_job(state: State<'_, ExportState>, job_id: String) -> Result<(), String> {
+    let mut queue = state.0.lock().unwrap();
+    match queue.jobs.iter().find(|j| j.id == job_id).map(|j| j.status) {
+        Some(ExportJobStatus::Queued) | Some(ExportJobStatus::Running) => Err("O job ainda não terminou".into()),
+        Some(_) => {
+            queue.jobs.retain(|j| j.id != job_id);
+            Ok(())
+        }
+        None => Err(format!("Job não encontrado: {}", job_id)),
+    }
+}
+
+// Puts the pending jobs saved in a project back in the queue (jobs that were running when
+// the app closed start over) and returns the whole queue
+#[tauri::command]
+fn load_export_queue(app_handle: tauri::AppHandle, project_path: String) -> Result<Vec<ExportJob>, String> {
+    let path = Path::new(&project_path).join("export_queue.json");
+    if path.exists() {
+        let saved = read_export_queue(&path)?;
+
+        let state = app_handle.state::<ExportState>();
+        let mut queue = state.0.lock().unwrap();
+        for job in saved {
+            if queue.jobs.iter().any(|j| j.id == job.id) {
+                continue;
+            }
+            queue.jobs.push(job);
+        }
+    }
+
+    pump_export_queue(&app_handle);
+    let jobs = app_handle.state::<ExportState>().0.lock().unwrap().jobs.clone();
+    Ok(jobs)
+}
+
+// Window of the dialogue level analysis used to bake the ducking
+const DUCKING_ANALYSIS_STEP: f64 = 0.05;
+
+// Runs the ducking of the export on the dialogue levels and returns it as volume keyframes
+// of every clip on a ducked music track, so it can be tweaked by hand. The UI should turn
+// the ducking of those tracks off after applying them, or it is applied twice.
+#[tauri::command]
+async fn bake_ducking_keyframes(
+    app_handle: tauri::AppHandle,
+    project_path: String,
+    clips: serde_json::Value,
+    tracks: serde_json::Value,
+) -> Result<Vec<BakedVolume>, String> {
+    let project_dir = std::path::PathBuf::from(&project_path);
+    let clips: Vec<Clip> = serde_json::from_value(clips).map_err(|e| format!("Clips inválidos: {}", e))?;
+    let tracks: Vec<Track> = serde_json::from_value(tracks).map_err(|e| format!("Tracks inválidas: {}", e))?;
+    let settings = read_project_settings(&project_dir)?;
+
+    let dialogue: Vec<Clip> = clips
+        .iter()
+        .filter(|c| !is_image_clip(c) && !is_text_clip(c) && !is_image_sequence_clip(c))
+        .filter(|c| track_role(&tracks, &c.track_id) == Some("dialogue"))
+        .cloned()
+        .collect();
+    if dialogue.is_empty() {
+        return Err("Nenhum clip de diálogo para analisar".into());
+    }
+
+    // Each bake writes its own files, removed whatever the analysis gives
+    let total_duration = timeline_duration(&clips);
+    let bake_id = next_export_job_id();
+    let graph_path = project_dir.join(format!("ducking_filter_{}.txt", bake_id));
+    let levels_path = project_dir.join(format!("ducking_levels_{}.txt", bake_id));
+    let report = measure_dialogue_levels(&app_handle, &dialogue, &tracks, &settings, total_duration, &graph_path, &levels_path).await;
+    remove_work_file(&graph_path);
+    remove_work_file(&levels_path);
+    let report = report?;
+
+    // "frame:0 pts:0 pts_time:0.05" followed by "lavfi.astats.Overall.RMS_level=-23.1"
+    let mut levels = Vec::new();
+    let mut time = None;
+    for line in report.lines() {
+        if let Some(pts) = line.split_whitespace().find_map(|part| part.strip_prefix("pts_time:")) {
+            time = pts.parse::<f64>().ok();
+        } else if let (Some(t), Some(level)) = (time, line.strip_prefix("lavfi.astats.Overall.RMS_level=")) {
+            levels.push((t, level.parse::<f64>().ok().filter(|l| l.is_finite()).unwrap_or(-120.0)));
+        }
+    }
+
+    Ok(clips
+        .iter()
+        .filter_map(|clip| {
+            let ducking = track_ducking(&tracks, &clip.track_id)?;
+            let reduction = ducking_gain_reduction(&levels, ducking);
+            Some(BakedVolume { clip_id: clip.id.clone(), keyframes: bake_clip_ducking(clip, &reduction) })
+        })
+        .collect())
+}
+
+// Renders the dialogue mix of the export with its RMS level printed to `levels_path` every
+// DUCKING_ANALYSIS_STEP, and returns that report
+async fn measure_dialogue_levels(
+    app_handle: &tauri::AppHandle,
+    dialogue: &[Clip],
+    tracks: &[Track],
+    settings: &ProjectSettings,
+    total_duration: f64,
+    graph_path: &Path,
+    levels_path: &Path,
+) -> Result<String, String> {
+    let graph = build_filter_graph(dialogue, tracks, total_duration, settings, None, None);
+    let graph = format!(
+        "{}[mix];[mix]aformat=channel_layouts=mono,asetnsamples=n={}:p=0,astats=metadata=1:reset=1,ametadata=mode=print:key=lavfi.astats.Overall.RMS_level:file={}[outa]",
+        graph.strip_suffix("[outa]").unwrap_or(&graph),
+        (settings.sample_rate as f64 * DUCKING_ANALYSIS_STEP).round() as u32,
+        escape_filter_value(&levels_path.to_string_lossy())
+    );
+    std::fs::write(graph_path, graph).map_err(|e| format!("Erro ao gravar o filtro de ducking no projeto: {}", e))?;
+
+    let output = app_handle
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| format!("Sidecar não encontrado: {}", e))?
+        .args(build_audio_analysis_args(dialogue, settings, total_duration, graph_path))
+        .output()
+        .await
+        .map_err(|e| format!("Falha ao iniciar o FFmpeg: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+
+    fs::read_to_string(levels_path).map_err(|e| format!("Erro ao ler os níveis do diálogo: {}", e))
+}
+
+// Share of the progress bar taken by the loudness measurement pass
+const LOUDNESS_PASS_PROGRESS: u32 = 30;
+
+// How an ffmpeg pass of the export ended. Finished keeps the end of stderr, where
+// loudnorm prints its measurement, Failed the error shown to the user.
+enum ExportPassEnd {
+    Finished(String),
+    Cancelled,
+    Failed(String),
+}
+
+// Runs one ffmpeg pass of a job, kept in ExportState so cancel_export can stop it. The
+// progress of the pass is reported scaled into `from..to` percent.
+async fn run_export_pass(app_handle: &tauri::AppHandle, job_id: &str, args: Vec<String>, total_duration: f64, from: u32, to: u32) -> ExportPassEnd {
+    let spawned = app_handle
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| format!("Sidecar não encontrado: {}", e))
+        .and_then(|command| command.args(args).spawn().map_err(|e| format!("Falha ao iniciar o FFmpeg: {}", e)));
+    let (mut rx, child) = match spawned {
+        Ok(spawned) => spawned,
+        Err(e) => return ExportPassEnd::Failed(e),
+    };
+
+    // Guardar processo para cancelamento, a não ser que o job tenha sido cancelado entre passes
+    {
+        let state = app_handle.state::<ExportState>();
+        let mut queue = state.0.lock().unwrap();
+        let cancelled = queue.jobs.iter().any(|j| j.id == job_id && j.status == ExportJobStatus::Cancelled);
+        if cancelled {
+            let _ = child.kill();
+            return ExportPassEnd::Cancelled;
+        }
+        queue.running.insert(job_id.to_string(), child);
+    }
+
+    // Monitorização do progresso (-progress pipe:1 no stdout, erros no stderr)
+    let mut last_percent = None;
+    let mut stderr_tail: Vec<String> = Vec::new();
+
+    while let Some(event) = rx.recv().await {
+        match event {
+            tauri_plugin_shell::process::CommandEvent::Stdout(line_bytes) => {
+                let raw = String::from_utf8_lossy(&line_bytes);
+                for line in raw.lines() {
+                    if let Some(percent) = parse_progress_line(line, total_duration) {
+                        let percent = from + percent * (to - from) / 100;
+                        if last_percent != Some(percent) {
+                            last_percent = Some(percent);
+                            update_export_job(app_handle, job_id, |job| job.progress = percent);
+                        }
+                    }
+                }
+            }
+            tauri_plugin_shell::process::CommandEvent::Stderr(line_bytes) => {
+                let raw = String::from_utf8_lossy(&line_bytes);
+                stderr_tail.extend(raw.lines().map(|l| l.to_string()));
+                if stderr_tail.len() > 40 {
+                    stderr_tail.drain(..stderr_tail.len() - 40);
+                }
+            }
+            tauri_plugin_shell::process::CommandEvent::Terminated(status) => {
+                println!("Renderização concluída com código: {:?}", status.code);
+
+                // O processo terminou, não há mais nada para cancelar
+                if let Ok(mut queue) = app_handle.state::<ExportState>().0.lock() {
+                    queue.running.remove(job_id);
+                }
+
+                // Killed by cancel_export ends with a signal on Unix and with exit code 1 on
+                // Windows, so the job state tells a cancel apart from an error
+                let cancelled = app_handle
+                    .state::<ExportState>()
+                    .0
+                    .lock()
+                    .map(|queue| queue.jobs.iter().any(|j| j.id == job_id && j.status == ExportJobStatus::Cancelled))
+                    .unwrap_or(false);
+                return if status.code == Some(0) {
+                    ExportPassEnd::Finished(stderr_tail.join("\n"))
+                } else if status.signal.is_some() || cancelled {
+                    ExportPassEnd::Cancelled
+                } else {
+                    let error_start = stderr_tail.len().saturating_sub(20);
+                    ExportPassEnd::Failed(stderr_tail[error_start..].join("\n"))
+                };
+            }
+            _ => {}
+        }
+    }
+
+    ExportPassEnd::Failed("FFmpeg terminou sem status".to_string())
+}
+
+
+impl Default for ProjectSettings {
+    fn default() -> Self {
+        ProjectSettings {
+            name: String::new(),
+            width: 1920,
+            height: 1080,
+            fps: 30.0,
+            background_color: "#000000".to_string(),
+            sample_rate: 44100,
+        }
+    }
+}
+
+// Clips of the timeline between `in_point` and `out_point`, cut to it and moved so the
+// range starts at zero. Keyframes keep their curve (times before the new start are fine for
+// the expressions), speed ramps restart from the speed they had at the cut. Fades that
+// were cut keep their duration, see FadeCut, and a transition into a clip whose head was
+// cut is dropped.
+fn clip_range(clips: &[Clip], in_point: f64, out_point: f64) -> Vec<Clip> {
+    clips
+        .iter()
+        .filter(|c| c.start < out_point && c.start + c.duration > in_point)
+        .map(|clip| {
+            let mut clip = clip.clone();
+            let head = (in_point - clip.start).max(0.0);
+            let tail = (clip.start + clip.duration - out_point).max(0.0);
+            clip.start = clip.start.max(in_point) - in_point;
+            clip.duration -= head + tail;
+            clip.fadeout = clip.fadeout.filter(|f| *f > tail);
+            clip.fadeoutAudio = clip.fadeoutAudio.filter(|f| *f > tail);
+            clip.fade_cut.video_out = if clip.fadeout.is_some() { tail } else { 0.0 };
+            clip.fade_cut.audio_out = if clip.fadeoutAudio.is_some() { tail } else { 0.0 };
+            if head <= 0.0 {
+                return clip;
+            }
+
+            let speed_kfs = clip_speed_keyframes(&clip).to_vec();
+            if speed_kfs.is_empty() {
+                clip.beginmoment += head;
+            } else {
+                let segments = speed_segments(&speed_kfs);
+                clip.beginmoment += source_time_at(&segments, head);
+                let segment = segments.iter().rev().find(|s| s.time <= head).unwrap_or(&segments[0]);
+                let speed_at_head = segment.speed + segment.slope * (head - segment.time);
+                // The new first keyframe continues the segment it was cut from (hold or ramp)
+                let interpolation = speed_kfs.iter()
+                    .filter(|k| k.time <= head)
+                    .max_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal))
+                    .map(|k| k.interpolation)
+                    .unwrap_or_default();
+
+                let mut speed: Vec<Keyframe> = speed_kfs.into_iter().filter(|k| k.time > head).collect();
+                speed.insert(0, Keyframe {
+                    id: "range-in".to_string(),
+                    time: head,
+                    value: speed_at_head,
+                    interpolation,
+                    bezier: None,
+                });
+                if let Some(k) = clip.keyframes.as_mut() {
+                    k.speed = Some(speed);
+                }
+            }
+            if let Some(keyframes) = clip.keyframes.as_mut() {
+                shift_keyframes(keyframes, head);
+            }
+
+            clip.fadein = clip.fadein.filter(|f| *f > head);
+            clip.fadeinAudio = clip.fadeinAudio.filter(|f| *f > head);
+            clip.fade_cut.video_in = if clip.fadein.is_some() { head } else { 0.0 };
+            clip.fade_cut.audio_in = if clip.fadeinAudio.is_some() { head } else { 0.0 };
+            clip.transition_in = None;
+            clip
+        })
+        .collect()
+}
+
+// Moves every keyframe `offset` seconds earlier in the clip
+fn shift_keyframes(keyframes: &mut Keyframes, offset: f64) {
+    let tracks = [
+        &mut keyframes.volume, &mut keyframes.opacity, &mut keyframes.speed, &mut keyframes.zoom,
+        &mut keyframes.position_x, &mut keyframes.position_y,
+        &mut keyframes.scale, &mut keyframes.scale_x, &mut keyframes.scale_y, &mut keyframes.rotation,
+        &mut keyframes.crop_left, &mut keyframes.crop_right, &mut keyframes.crop_top, &mut keyframes.crop_bottom,
+        &mut keyframes.exposure, &mut keyframes.contrast, &mut keyframes.saturation,
+        &mut keyframes.temperature, &mut keyframes.tint,
+    ];
+    for track in tracks.into_iter().flatten() {
+        for keyframe in track.iter_mut() {
+            keyframe.time -= offset;
+        }
+    }
+    for keyframe in keyframes.rotation3d.iter_mut().flatten() {
+        keyframe.time -= offset;
+    }
+    for keyframe in keyframes.position.iter_mut().flatten() {
+        keyframe.time -= offset;
+    }
+}
+
+// fade filters of a clip, in clip time (0 at its start). `alpha` is ":alpha=1" to fade the
+// transparency instead of to black. A fade in cut by clip_range runs on timestamps moved
+// forward by the cut, fade does not take a negative start.
+fn build_video_fades(clip: &Clip, alpha: &str) -> Vec<String> {
+    let mut fades = Vec::new();
+    if let Some(fi) = clip.fadein.filter(|f| *f > 0.0) {
+        let cut = clip.fade_cut.video_in;
+        if cut > 0.0 {
+            fades.push(format!(
+                "setpts=PTS+{c:.4}/TB,fade=t=in:st=0:d={:.4}{},setpts=PTS-{c:.4}/TB",
+                fi, alpha, c = cut
+            ));
+        } else {
+            fades.push(format!("fade=t=in:st=0:d={:.4}{}", fi, alpha));
+        }
+    }
+    if let Some(fo) = clip.fadeout.filter(|f| *f > 0.0) {
+        let st = clip.duration + clip.fade_cut.video_out - fo;
+        fades.push(format!("fade=t=out:st={:.4}:d={:.4}{}", st, fo, alpha));
+    }
+    fades
+}
+
+// Same as build_video_fades for the afade of the audio
+fn build_audio_fades(clip: &Clip) -> Vec<String> {
+    let mut fades = Vec::new();
+    if let Some(fi) = clip.fadeinAudio.filter(|f| *f > 0.0) {
+        let cut = clip.fade_cut.audio_in;
+        if cut > 0.0 {
+            fades.push(format!(
+                "asetpts=PTS+{c:.4}/TB,afade=t=in:st=0:d={:.4},asetpts=PTS-{c:.4}/TB",
+                fi, c = cut
+            ));
+        } else {
+            fades.push(format!("afade=t=in:st=0:d={:.4}", fi));
+        }
+    }
+    if let Some(fo) = clip.fadeoutAudio.filter(|f| *f > 0.0) {
+        let st = clip.duration + clip.fade_cut.audio_out - fo;
+        fades.push(format!("afade=t=out:st={:.4}:d={:.4}", st, fo));
+    }
+    fades
+}
+
+// Converts the color saved by the UI ("#RRGGBB", "#RRGGBBAA" or a named color)
+// to a value accepted by the ffmpeg `color` source
+fn ffmpeg_color(color: &str) -> String {
+    let color = color.trim();
+    if color.is_empty() {
+        return "black".to_string();
+    }
+    match color.strip_prefix('#') {
+        Some(hex) if hex.chars().all(|c| c.is_ascii_hexdigit()) => format!("0x{}", hex),
+        _ => color.to_string(),
+    }
+}
+
+pub fn build_rendering_filter(
+    clips: &[Clip],
+    tracks: &[Track],
+    total_duration: f64,
+    settings: &ProjectSettings,
+    pixel_format: &str,
+    output_size: Option<(u32, u32)>,
+) -> String {
+    build_filter_graph(clips, tracks, total_duration, settings, Some(pixel_format), output_size)
+}
+
+// Graph of the whole timeline, `[outv]` (in `pixel_format`) and `[outa]`. Without a pixel
+// format only the audio part is built, for the passes that just analyse the mix. The
+// timeline is composed at the project size, `output_size` scales only the final picture.
+fn build_filter_graph(
+    clips: &[Clip],
+    tracks: &[Track],
+    total_duration: f64,
+    settings: &ProjectSettings,
+    pixel_format: Option<&str>,
+    output_size: Option<(u32, u32)>,
+) -> String {
+    let mut filters = Vec::new();
+    let mut audio_outputs = Vec::new();
+    let mut video_layers = Vec::new();
+
+    let (width, height) = (settings.width, settings.height);
+    let centering_filter = format!(
+        "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2:color=black@0",
+        w = width, h = height
+    );
+
+    let plan = plan_transitions(clips);
+
+    for (i, clip) in clips.iter().enumerate() {
+        // Text clips are rendered like stills, drawn over their transparent input
+        let is_text = is_text_clip(clip);
+        let is_image = is_image_clip(clip) || is_text;
+        let is_audio = is_audio_clip(clip) && !is_text;
+        // Sequences are trimmed like videos but have no sound
+        let has_audio = !is_image && !is_image_sequence_clip(clip) && !is_animated_clip(clip);
+        let edges = &plan[i];
+        let (video_enabled, audio_enabled) = track_output_flags(tracks, &clip.track_id);
+
+        // --- Processamento de Vídeo/Imagem ---
+        if !is_audio && video_enabled && pixel_format.is_some() {
+            let mut v_filters = Vec::new();
+
+        //check if has keyframes, if result 1 there is no keyframes
+
+        let opacity_kfs = clip.keyframes.as_ref()
+                .and_then(|k| k.opacity.as_ref())
+                .map(|v| v.as_slice())
+                .unwrap_or(&[]);
+
+        let opacity_expression = build_opacity_expression(opacity_kfs);
+
+        // Speed keyframes remap the trimmed source onto the clip's timeline duration
+        let speed_kfs = clip_speed_keyframes(clip);
+        let speed_filter = if speed_kfs.is_empty() {
+            String::new()
+        } else {
+            format!(",setpts='({})/TB',fps={}", build_speed_pts_expression(speed_kfs), settings.fps)
+        };
+        let source_duration = clip_source_duration(clip);
+
+        // Transition handles come from the source when possible, otherwise the edge frame is frozen
+        let (pre_src, post_src) = source_handles(clip, edges);
+        let pts_shift = if pre_src > 0.0 { format!("-{:.4}/TB", pre_src) } else { String::new() };
+
+        // Clips inside a transition run are rebased to the start of their handle and joined
+        // with xfade later, the others are positioned on the timeline right away
+        let v_tail = if edges.prev.is_some() || edges.next.is_some() {
+            build_transition_video_tail(i, clips, edges, pre_src, settings)
+        } else if is_image {
+            format!(",setpts=PTS-STARTPTS+{}/TB[v{}]", clip.start, i)
+        } else {
+            format!(",setpts=PTS+{}/TB[v{}]", clip.start, i)
+        };
+
+        // Transform and 3D rotation replace the plain centering with a perspective warp
+        let layer_filter = build_transform_filter(clip, i, settings, pre_src)
+            .unwrap_or_else(|| centering_filter.clone());
+        // Grading goes on the source, before it is placed on the canvas
+        let layer_filter = match build_color_filter(clip, i, settings, pre_src) {
+            Some(color_filter) => format!("{},{}", color_filter, layer_filter),
+            None => layer_filter,
+        };
+        // The key is pulled from the untouched source, before the grading
+        let layer_filter = match build_chroma_key_filter(clip) {
+            Some(key_filter) => format!("{},{}", key_filter, layer_filter),
+            None => layer_filter,
+        };
+        let layer_filter = if is_text {
+            format!("{},{}", build_text_filter(clip, settings), layer_filter)
+        } else {
+            layer_filter
+        };
+
+        if opacity_expression == "1" 
+        {
+            
+            if is_image {
+
+                                
+                    v_filters.push("format=yuva420p".to_string());
+                    v_filters.extend(build_video_fades(clip, ":alpha=1"));
+
+                    let v_effects = if v_filters.is_empty() { "".to_string() } else { format!(",{}", v_filters.join(",")) };
 
                     
                     filters.push(format!(
-                        "[{}:v]{}{},setpts=PTS-STARTPTS+{}/TB[v{}]",
-                        i, centering_filter, v_effects, clip.start, i
+                        "[{}:v]{}{}{}",
+                        i, layer_filter, v_effects, v_tail
                     ));
                             
-            } else 
-            {
-                        if let Some(fi) = clip.fadein {
-                            if fi > 0.0 { v_filters.push(format!("fade=t=in:st=0:d={:.4}", fi)); }
-                        }
-                        if let Some(fo) = clip.fadeout {
-                            if fo > 0.0 {
-                                let st = clip.duration - fo;
-                                v_filters.push(format!("fade=t=out:st={:.4}:d={:.4}", st, fo));
-                            }
-                        }
+            } else {
+                        // Animations keep their transparency and fade through it like stills
+                        let alpha = if is_animated_clip(clip) {
+                            v_filters.push("format=yuva420p".to_string());
+                            ":alpha=1"
+                        } else {
+                            ""
+                        };
+                        v_filters.extend(build_video_fades(clip, alpha));
 
                         let v_effects = if v_filters.is_empty() { "".to_string() } else { format!(",{}", v_filters.join(",")) };
 
-                        filters.push(format!(
-                            "[{}:v]trim=start={:.4}:duration={:.4},setpts=PTS-STARTPTS,{}{},setpts=PTS+{}/TB[v{}]",
-                            i, clip.beginmoment, clip.duration, centering_filter, v_effects, clip.start, i
-                        ));
-            }
-                
+                        filters.push(format!(
+                            "[{}:v]trim=start={:.4}:duration={:.4},setpts=PTS-STARTPTS{}{},{}{}{}",
+                            i, clip.beginmoment - pre_src, source_duration + pre_src + post_src,
+                            pts_shift, speed_filter, layer_filter, v_effects, v_tail
+                        ));
+            }
+                
+        }
+        else {
+            // --- Logic for Keyframe Opacity ---
+            
+            // Alpha multiplied frame by frame (rgba for colorchannelmixer), the frames start
+            // at -pre_src after the shift
+            v_filters.push(build_opacity_filter(i, opacity_kfs, -pre_src, clip.duration, settings.fps as f64));
+
+            let v_effects = format!(",{}", v_filters.join(","));
+
+            if is_image {
+                // For images: reset PTS so 't' in the expression starts at 0 for this clip
+                filters.push(format!(
+                    "[{}:v]{}{}{}",
+                    i, layer_filter, v_effects, v_tail
+                ));
+            } else {
+                // For videos: trim first, then apply effects and timeline positioning
+                filters.push(format!(
+                    "[{}:v]trim=start={:.4}:duration={:.4},setpts=PTS-STARTPTS{}{},{}{}{}",
+                    i, clip.beginmoment - pre_src, source_duration + pre_src + post_src,
+                    pts_shift, speed_filter, layer_filter, v_effects, v_tail
+                ));
+            }
+        }
+
+
+
+          let z_order = track_z_order(tracks, &clip.track_id);
+          let blend = blend_mode_filter(clip.blendmode.as_deref());
+          if edges.prev.is_none() && edges.next.is_none() {
+              video_layers.push((z_order, format!("v{}", i), clip.start, clip.start + clip.duration, blend));
+          } else if edges.prev.is_none() {
+              // First clip of a transition run: the whole run becomes a single layer, blended
+              // with the mode of that clip
+              let (layer, start, end) = build_transition_run(i, clips, &plan, settings, &mut filters);
+              video_layers.push((z_order, layer, start, end, blend));
+          }
+        }
+
+        // --- Processamento de Áudio com Keyframes ---
+        if has_audio && audio_enabled {
+            // Audio handles around transitions, crossfaded by the fades below once mixed by amix
+            let (pre_src, post_src) = source_handles(clip, edges);
+            let delay_ms = ((clip.start - pre_src) * 1000.0).round() as i64;
+            let a_transition = build_transition_audio_fades(clip, edges, pre_src, post_src);
+            
+            let vol_kfs = clip.keyframes.as_ref()
+                .and_then(|k| k.volume.as_ref())
+                .map(|v| v.as_slice())
+                .unwrap_or(&[]);
+
+            let volume_expr = build_volume_expression(vol_kfs, clip.mute.unwrap_or(false));
+
+            // Trimmed (and speed remapped) source, every chain below continues from it
+            let a_source = build_audio_source(i, clip, pre_src, post_src)
+                + &build_audio_effects(clip, settings.sample_rate);
+
+
+            println!("--- Comando {} ---", volume_expr);
+
+            //if volume equal 1 is because there is no keyframes audio
+
+            if volume_expr == "1"
+            {
+
+                println!("São iguais!");
+                    let volume = if clip.mute.unwrap_or(false) { "0" } else { "1" };
+                    
+                    let a_effects = build_audio_fades(clip);
+
+                    let a_effects_str = if a_effects.is_empty() { String::new() } else { format!(",{}", a_effects.join(",")) };
+
+                    filters.push(format!(
+                        "{},volume={}{}{},adelay={}|{},aresample={}:async=1[a{}]",
+                        a_source, volume, a_effects_str, a_transition, delay_ms, delay_ms, settings.sample_rate, i
+                    ));
+                    audio_outputs.push((format!("[a{}]", i), clip.track_id.clone()));
+            }
+            else
+            {
+
+                let mut a_filters = Vec::new();
+                // 1. O corte do áudio original (atrim + asetpts) já vem em a_source
+
+                // 2. Aplicamos o volume (dinâmico por keyframes ou fixo)
+                a_filters.push(format!("volume=eval=frame:volume='{}'", volume_expr));
+
+                // Crossfades das transições (a string já começa com vírgula)
+                if !a_transition.is_empty() {
+                    a_filters.push(a_transition[1..].to_string());
+                }
+
+                // 3. Aplicamos o delay para posicionar na timeline
+                a_filters.push(format!("adelay={}|{}", delay_ms, delay_ms));
+
+                // Resample to the project rate so every input of amix matches
+                a_filters.push(format!("aresample={}", settings.sample_rate));
+
+                // 4. Unimos os filtros e definimos a saída [aX]
+                let filter_string = a_filters.join(",");
+                filters.push(format!("{},{}[a{}]", a_source, filter_string, i));
+                
+                audio_outputs.push((format!("[a{}]", i), clip.track_id.clone()));
+            }
+        }
+    }
+
+    // --- Composição Final ---
+    if let Some(pixel_format) = pixel_format {
+        filters.push(format!(
+            "color=s={}x{}:c={}:r={}:d={:.4}[bg]",
+            width, height, ffmpeg_color(&settings.background_color), settings.fps, total_duration
+        ));
+    
+        // Bottom track first; the sort is stable so clips of one track keep the timeline order
+        video_layers.sort_by_key(|layer| layer.0);
+
+        let mut current_v_layer = "bg".to_string();
+        for (idx, (_, layer, start, end, blend)) in video_layers.iter().enumerate() {
+            let next_v_layer = if idx == video_layers.len() - 1 { "outv_pre".to_string() } else { format!("l{}", idx) };
+            if let Some(blend) = blend {
+                filters.push(build_blend_layer(idx, &current_v_layer, layer, *start, *end, blend, &next_v_layer));
+                current_v_layer = next_v_layer;
+                continue;
+            }
+            filters.push(format!(
+                "[{}] [{}] overlay=enable='between(t,{:.4},{:.4})' [ {} ]",
+                current_v_layer, layer, start, end, next_v_layer
+            ));
+            current_v_layer = next_v_layer;
+        }
+    
+        let scale = output_size.map(|(w, h)| format!("scale={}:{},", w, h)).unwrap_or_default();
+        filters.push(format!(
+            "[{}]{}format={}[outv]",
+            if video_layers.is_empty() { "bg" } else { "outv_pre" }, scale, pixel_format
+        ));
+    }
+
+    if audio_outputs.is_empty() {
+        filters.push(format!("anullsrc=r={}:cl=stereo:d={:.4}[outa]", settings.sample_rate, total_duration));
+    } else {
+        let audio_outputs = build_ducking(&audio_outputs, tracks, total_duration, &mut filters);
+        filters.push(format!(
+            "{}amix=inputs={}:{}[outa]",
+            audio_outputs.join(""),
+            audio_outputs.len(),
+            AMIX_OPTIONS
+        ));
+    }
+
+    filters.join(";")
+}
+
+
+// Options of every amix in the graph
+const AMIX_OPTIONS: &str = "duration=longest:dropout_transition=99999";
+
+// loudnorm at the end of the mix. Without `measured` it only analyses (measurement pass),
+// with it the gain is corrected linearly from the measured values. loudnorm works at
+// 192 kHz, so the project rate is restored after it.
+fn append_loudnorm(graph: &str, target: &LoudnessTarget, measured: Option<&LoudnessMeasurement>, sample_rate: u32) -> String {
+    let mut loudnorm = format!(
+        "loudnorm=I={:.1}:TP={:.1}:LRA={:.1}",
+        target.integrated.clamp(-70.0, -5.0),
+        target.true_peak.clamp(-9.0, 0.0),
+        target.lra.clamp(1.0, 50.0)
+    );
+    match measured {
+        Some(m) => loudnorm.push_str(&format!(
+            ":measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true:print_format=summary",
+            m.input_i, m.input_tp, m.input_lra, m.input_thresh, m.target_offset
+        )),
+        None => loudnorm.push_str(":print_format=json"),
+    }
+
+    // loudnorm sets the final level, so the mixes are summed without amix's scaling
+    let graph = graph.replace(AMIX_OPTIONS, &format!("{}:normalize=0", AMIX_OPTIONS));
+    let mix = graph.strip_suffix("[outa]").unwrap_or(&graph);
+    format!("{}[mix];[mix]{},aresample={}[outa]", mix, loudnorm, sample_rate)
+}
+
+// JSON block printed by loudnorm (print_format=json) at the end of the measurement pass
+fn parse_loudnorm_output(stderr: &str) -> Option<LoudnessMeasurement> {
+    let start = stderr.rfind('{')?;
+    let end = stderr[start..].find('}')? + start;
+    serde_json::from_str(&stderr[start..=end]).ok()
+}
+
+// Escapes a value for a filter option inside the graph: once for the option parser
+// (\\ ' :) and once more for the graph parser (\\ ' [ ] , ;)
+fn escape_filter_value(value: &str) -> String {
+    let mut option = String::new();
+    for c in value.chars() {
+        if matches!(c, '\\' | '\'' | ':') {
+            option.push('\\');
+        }
+        option.push(c);
+    }
+    let mut graph = String::new();
+    for c in option.chars() {
+        if matches!(c, '\\' | '\'' | '[' | ']' | ',' | ';') {
+            graph.push('\\');
+        }
+        graph.push(c);
+    }
+    graph
+}
+
+// drawtext chain of a text clip, the input is a transparent canvas of the project size
+fn build_text_filter(clip: &Clip, settings: &ProjectSettings) -> String {
+    let style = match &clip.text {
+        Some(style) => style.clone(),
+        None => return "format=rgba".to_string(),
+    };
+    let margin = style.margin.unwrap_or(0.0).max(0.0);
+
+    let x = match style.align.as_deref() {
+        Some("left") => format!("{:.2}", margin),
+        Some("right") => format!("w-text_w-{:.2}", margin),
+        _ => "(w-text_w)/2".to_string(),
+    };
+    let y = match style.vertical_align.as_deref() {
+        Some("top") => format!("{:.2}", margin),
+        Some("bottom") => format!("h-text_h-{:.2}", margin),
+        _ => "(h-text_h)/2".to_string(),
+    };
+
+    // Literal text: expansion is off so % is not read as a function
+    let mut options = vec![
+        format!("text={}", escape_filter_value(style.content.trim_end())),
+        "expansion=none".to_string(),
+        format!("fontsize={:.2}", style.font_size.unwrap_or(settings.height as f64 / 12.0)),
+        format!("fontcolor={}", ffmpeg_color(style.color.as_deref().unwrap_or("white"))),
+        format!("x={}", escape_filter_value(&x)),
+        format!("y={}", escape_filter_value(&y)),
+    ];
+    if let Some(font) = style.font_file.as_deref().filter(|f| !f.is_empty()) {
+        options.push(format!("fontfile={}", escape_filter_value(font)));
+    }
+    if let Some(spacing) = style.line_spacing {
+        options.push(format!("line_spacing={:.2}", spacing));
+    }
+    if let Some(width) = style.outline_width.filter(|w| *w > 0.0) {
+        options.push(format!("borderw={:.2}", width));
+        options.push(format!("bordercolor={}", ffmpeg_color(style.outline_color.as_deref().unwrap_or("black"))));
+    }
+    if style.shadow_color.is_some() || style.shadow_x.is_some() || style.shadow_y.is_some() {
+        options.push(format!("shadowcolor={}", ffmpeg_color(style.shadow_color.as_deref().unwrap_or("black@0.6"))));
+        options.push(format!("shadowx={:.2}", style.shadow_x.unwrap_or(2.0)));
+        options.push(format!("shadowy={:.2}", style.shadow_y.unwrap_or(2.0)));
+    }
+    if let Some(color) = style.box_color.as_deref().filter(|c| !c.is_empty()) {
+        options.push("box=1".to_string());
+        options.push(format!("boxcolor={}", ffmpeg_color(color)));
+        options.push(format!("boxborderw={:.0}", style.box_padding.unwrap_or(0.0).max(0.0)));
+    }
+
+    format!("format=rgba,drawtext={}", options.join(":"))
+}
+
+// blend options for a clip's blend mode, None for normal (plain overlay) or unknown modes.
+// The composite below is input A and the clip input B, so the modes that ffmpeg defines
+// with A as the blend layer (dodge, burn, soft light) are written as expressions.
+fn blend_mode_filter(mode: Option<&str>) -> Option<String> {
+    let mode: String = mode?.to_lowercase().chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    let blend = match mode.as_str() {
+        "multiply" | "screen" | "overlay" | "difference" | "darken" | "lighten"
+        | "exclusion" | "subtract" | "hardlight" => format!("all_mode={}", mode),
+        "add" | "addition" | "lineardodge" => "all_mode=addition".to_string(),
+        "softlight" => "all_expr='(255-2*B)*A*A/65025+2*B*A/255'".to_string(),
+        "colordodge" | "dodge" => "all_expr='if(gte(B,255),255,min(255,A*255/(255-B)))'".to_string(),
+        "colorburn" | "burn" => "all_expr='if(lte(B,0),0,max(0,255-(255-A)*255/B))'".to_string(),
+        _ => return None,
+    };
+    Some(blend)
+}
+
+// Blends `layer` with the composite `below` in RGB, then puts the result back over the
+// composite through the layer's own alpha, so transparent areas, fades and opacity still
+// apply. The layer only exists between `start` and `end`, blend and overlay are enabled
+// there and pass the composite through elsewhere.
+fn build_blend_layer(idx: usize, below: &str, layer: &str, start: f64, end: f64, blend: &str, output: &str) -> String {
+    let enable = format!("enable='between(t,{:.4},{:.4})'", start, end);
+    [
+        format!("[{}]split[bb{n}][bu{n}]", below, n = idx),
+        format!("[{}]format=gbrap,split[bt{n}][bm{n}]", layer, n = idx),
+        format!("[bu{n}]format=gbrap[bg{n}]", n = idx),
+        format!("[bg{n}][bt{n}]blend={}:{}[bx{n}]", blend, enable, n = idx),
+        format!("[bm{n}]alphaextract[ba{n}]", n = idx),
+        format!("[bx{n}][ba{n}]alphamerge[bl{n}]", n = idx),
+        format!("[bb{n}][bl{n}]overlay={}[{}]", enable, output, n = idx),
+    ]
+    .join(";")
+}
+
+// Whether the clips of a track reach the video overlays and the audio mix. Hidden tracks
+// lose their video, muted tracks their audio, and once any track is soloed only the soloed
+// tracks are rendered. Without track information everything is rendered.
+fn track_output_flags(tracks: &[Track], track_id: &str) -> (bool, bool) {
+    let any_solo = tracks.iter().any(|t| t.solo.unwrap_or(false));
+    let track = match tracks.iter().find(|t| t.id == track_id) {
+        Some(track) => track,
+        None => return (!any_solo, !any_solo),
+    };
+
+    let soloed_out = any_solo && !track.solo.unwrap_or(false);
+    (
+        !track.hidden.unwrap_or(false) && !soloed_out,
+        !track.mute.unwrap_or(false) && !soloed_out,
+    )
+}
+
+fn track_role<'a>(tracks: &'a [Track], track_id: &str) -> Option<&'a str> {
+    tracks.iter().find(|t| t.id == track_id).and_then(|t| t.role.as_deref())
+}
+
+// Ducking of a track, when it is a music track with ducking enabled
+fn track_ducking<'a>(tracks: &'a [Track], track_id: &str) -> Option<&'a Ducking> {
+    tracks
+        .iter()
+        .find(|t| t.id == track_id && t.role.as_deref() == Some("music"))
+        .and_then(|t| t.ducking.as_ref())
+        .filter(|d| d.enabled.unwrap_or(true))
+}
+
+// Sidechain ducking before the final mix. The dialogue clips are mixed into one key, each
+// ducked music track is mixed and compressed by a copy of it; both are padded to the
+// timeline so the compressor never waits for a missing input. Returns the labels that go
+// to the final amix (unchanged when there is nothing to duck).
+fn build_ducking(audio_outputs: &[(String, String)], tracks: &[Track], total_duration: f64, filters: &mut Vec<String>) -> Vec<String> {
+    let dialogue: Vec<&String> = audio_outputs
+        .iter()
+        .filter(|(_, track_id)| track_role(tracks, track_id) == Some("dialogue"))
+        .map(|(label, _)| label)
+        .collect();
+
+    let mut ducked_tracks: Vec<&Track> = tracks
+        .iter()
+        .filter(|t| track_ducking(tracks, &t.id).is_some())
+        .filter(|t| audio_outputs.iter().any(|(_, track_id)| *track_id == t.id))
+        .collect();
+    if dialogue.is_empty() {
+        ducked_tracks.clear();
+    }
+    if ducked_tracks.is_empty() {
+        return audio_outputs.iter().map(|(label, _)| label.clone()).collect();
+    }
+
+    // Dialogue submix: one copy goes to the final mix, one keys each ducked track
+    let keys: String = (0..ducked_tracks.len()).map(|k| format!("[dk{}]", k)).collect();
+    filters.push(format!(
+        "{}amix=inputs={}:{},apad=whole_dur={:.4},asplit={}[dlg]{}",
+        dialogue.iter().map(|l| l.as_str()).collect::<String>(),
+        dialogue.len(),
+        AMIX_OPTIONS,
+        total_duration,
+        ducked_tracks.len() + 1,
+        keys
+    ));
+
+    let mut outputs = vec!["[dlg]".to_string()];
+    for (k, track) in ducked_tracks.iter().enumerate() {
+        let ducking = track_ducking(tracks, &track.id).unwrap();
+        let music: Vec<&String> = audio_outputs.iter().filter(|(_, id)| *id == track.id).map(|(label, _)| label).collect();
+        filters.push(format!(
+            "{}amix=inputs={}:{},apad=whole_dur={:.4}[dm{k}];[dm{k}][dk{k}]sidechaincompress=threshold={:.6}:ratio={:.2}:attack={:.2}:release={:.2}[dd{k}]",
+            music.iter().map(|l| l.as_str()).collect::<String>(),
+            music.len(),
+            AMIX_OPTIONS,
+            total_duration,
+            db_to_linear(ducking.threshold.unwrap_or(-30.0)).clamp(0.000976563, 1.0),
+            ducking.ratio.unwrap_or(6.0).clamp(1.0, 20.0),
+            ducking.attack.unwrap_or(20.0).clamp(0.01, 2000.0),
+            ducking.release.unwrap_or(300.0).clamp(0.01, 9000.0),
+            k = k
+        ));
+        outputs.push(format!("[dd{}]", k));
+    }
+
+    // Everything that is neither dialogue nor ducked goes to the final mix as is
+    outputs.extend(
+        audio_outputs
+            .iter()
+            .filter(|(label, track_id)| !dialogue.contains(&label) && !ducked_tracks.iter().any(|t| t.id == *track_id))
+            .map(|(label, _)| label.clone()),
+    );
+    outputs
+}
+
+// Gain reduction (dB, >= 0) of a ducking compressor fed with the dialogue `levels`
+// (time, RMS dB) measured every few milliseconds, smoothed with attack and release
+fn ducking_gain_reduction(levels: &[(f64, f64)], ducking: &Ducking) -> Vec<(f64, f64)> {
+    let threshold = ducking.threshold.unwrap_or(-30.0);
+    let ratio = ducking.ratio.unwrap_or(6.0).clamp(1.0, 20.0);
+    let attack = ducking.attack.unwrap_or(20.0).max(0.01) / 1000.0;
+    let release = ducking.release.unwrap_or(300.0).max(0.01) / 1000.0;
+
+    let mut reduction = 0.0;
+    let mut last_time = levels.first().map(|l| l.0).unwrap_or(0.0);
+    levels
+        .iter()
+        .map(|&(time, level)| {
+            let target = (level - threshold).max(0.0) * (1.0 - 1.0 / ratio);
+            let dt = (time - last_time).max(0.0);
+            let constant = if target > reduction { attack } else { release };
+            reduction += (target - reduction) * (1.0 - (-dt / constant).exp());
+            last_time = time;
+            (time, reduction)
+        })
+        .collect()
+}
+
+// Value of a volume keyframe track at clip time `t`. Without keyframes the clip plays at
+// 0 dB, value 0.5.
+fn volume_value_at(keyframes: &[Keyframe], t: f64) -> f64 {
+    keyframe_value_at(keyframes, t, 0.5)
+}
+
+// Value of a keyframe track at clip time `t`, with the curve of each keyframe as the export
+// renders it (same result as build_interpolation_expression). `default` without keyframes.
+fn keyframe_value_at(keyframes: &[Keyframe], t: f64, default: f64) -> f64 {
+    let mut sorted = keyframes.to_vec();
+    sorted.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal));
+    match (sorted.first(), sorted.last()) {
+        (None, _) | (_, None) => default,
+        (Some(first), _) if t <= first.time => first.value,
+        (_, Some(last)) if t >= last.time => last.value,
+        _ => {
+            let next = sorted.iter().position(|k| k.time > t).unwrap();
+            let (a, b) = (&sorted[next - 1], &sorted[next]);
+            a.value + (b.value - a.value) * eased_progress(a, (t - a.time) / (b.time - a.time))
+        }
+    }
+}
+
+// Volume keyframes of a music clip with the ducking applied on top of its own volume.
+// Points closer than half a dB to the last kept one are dropped, the point right before
+// the next change is kept so flat stretches stay flat.
+fn bake_clip_ducking(clip: &Clip, reduction: &[(f64, f64)]) -> Vec<Keyframe> {
+    let own = clip.keyframes.as_ref().and_then(|k| k.volume.as_ref()).map(|v| v.as_slice()).unwrap_or(&[]);
+    let end = clip.start + clip.duration;
+    let inside: Vec<&(f64, f64)> = reduction.iter().filter(|(t, _)| *t >= clip.start && *t <= end).collect();
+
+    let mut keyframes: Vec<Keyframe> = Vec::new();
+    let mut skipped: Option<Keyframe> = None;
+    for (n, (time, db)) in inside.iter().enumerate() {
+        let local = time - clip.start;
+        let keyframe = Keyframe {
+            id: format!("duck-{}-{}", clip.id, n),
+            time: local,
+            value: (volume_value_at(own, local) - db / 100.0).max(0.0),
+            interpolation: Interpolation::Linear,
+            bezier: None,
+        };
+        let is_edge = n == 0 || n == inside.len() - 1;
+        if !is_edge && keyframes.last().map(|k| (k.value - keyframe.value).abs() < 0.005).unwrap_or(false) {
+            skipped = Some(keyframe);
+            continue;
+        }
+        keyframes.extend(skipped.take());
+        keyframes.push(keyframe);
+    }
+    keyframes
+}
+
+// Sort key of a track in the overlay stack, lower is drawn first (further down).
+// Clips of unknown tracks go under every listed track, even those with a negative `zIndex`.
+fn track_z_order(tracks: &[Track], track_id: &str) -> (i32, i64) {
+    match tracks.iter().position(|t| t.id == track_id) {
+        Some(pos) => (tracks[pos].z_index.unwrap_or(0), -(pos as i64)),
+        None => (i32::MIN, i64::MIN),
+    }
+}
+
+// Transitions supported by ffmpeg's xfade filter
+const XFADE_TRANSITIONS: &[&str] = &[
+    "fade", "wipeleft", "wiperight", "wipeup", "wipedown",
+    "slideleft", "slideright", "slideup", "slidedown",
+    "circlecrop", "rectcrop", "distance", "fadeblack", "fadewhite", "radial",
+    "smoothleft", "smoothright", "smoothup", "smoothdown",
+    "circleopen", "circleclose", "vertopen", "vertclose", "horzopen", "horzclose",
+    "dissolve", "pixelize", "diagtl", "diagtr", "diagbl", "diagbr",
+    "hlslice", "hrslice", "vuslice", "vdslice", "hblur", "fadegrays",
+    "wipetl", "wipetr", "wipebl", "wipebr", "squeezeh", "squeezev", "zoomin",
+];
+
+// Maximum gap (in seconds) between two clips still considered adjacent for a transition
+const TRANSITION_ADJACENCY: f64 = 0.05;
+
+// Transitions touching one clip: the clip it comes from / goes to and
+// the handle (half of the transition) needed before and after it
+#[derive(Debug, Clone, Default)]
+struct ClipEdges {
+    prev: Option<usize>,
+    next: Option<usize>,
+    pre: f64,
+    post: f64,
+}
+
+// Pairs every `transition_in` with the clip that ends where it starts on the same track
+fn plan_transitions(clips: &[Clip]) -> Vec<ClipEdges> {
+    let mut plan = vec![ClipEdges::default(); clips.len()];
+
+    for (j, clip) in clips.iter().enumerate() {
+        let transition = match &clip.transition_in {
+            Some(t) if t.duration > 0.0 => t,
+            _ => continue,
+        };
+
+        let prev = clips.iter()
+            .enumerate()
+            .filter(|(k, c)| {
+                *k != j
+                    && c.track_id == clip.track_id
+                    && plan[*k].next.is_none()
+                    && is_audio_clip(c) == is_audio_clip(clip)
+                    && (c.start + c.duration - clip.start).abs() <= TRANSITION_ADJACENCY
+            })
+            .max_by(|a, b| (a.1.start + a.1.duration).partial_cmp(&(b.1.start + b.1.duration)).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(k, _)| k);
+
+        if let Some(k) = prev {
+            // The transition can not be longer than either clip
+            let half = transition.duration.min(clips[k].duration).min(clip.duration) / 2.0;
+            plan[k].next = Some(j);
+            plan[k].post = half;
+            plan[j].prev = Some(k);
+            plan[j].pre = half;
         }
-        else {
-            // --- Logic for Keyframe Opacity ---
-            
-            // 1. Ensure alpha channel support for the pixel format
-            v_filters.push("format=yuva420p".to_string());
-            
-            // 2. Add the opacity expression. 
-            // We use single quotes for the internal math expression
-            v_filters.push(format!("colorchannelmixer=aa='{}'", opacity_expression));
+    }
 
-            let v_effects = format!(",{}", v_filters.join(","));
+    plan
+}
 
-            if is_image {
-                // For images: reset PTS so 't' in the expression starts at 0 for this clip
-                filters.push(format!(
-                    "[{}:v]{}{},setpts=PTS-STARTPTS+{}/TB[v{}]",
-                    i, centering_filter, v_effects, clip.start, i
-                ));
-            } else {
-                // For videos: trim first, then apply effects and timeline positioning
-                filters.push(format!(
-                    "[{}:v]trim=start={:.4}:duration={:.4},setpts=PTS-STARTPTS,{}{},setpts=PTS+{}/TB[v{}]",
-                    i, clip.beginmoment, clip.duration, centering_filter, v_effects, clip.start, i
-                ));
-            }
-        }
+// Handles (before, after) that can be read from the source file. Speed ramps and stills
+// have none, their edges are frozen instead
+fn source_handles(clip: &Clip, edges: &ClipEdges) -> (f64, f64) {
+    if is_image_clip(clip) || is_text_clip(clip) || !clip_speed_keyframes(clip).is_empty() {
+        return (0.0, 0.0);
+    }
+    (edges.pre.min(clip.beginmoment.max(0.0)), edges.post)
+}
 
+fn xfade_transition_name(transition: &Transition) -> String {
+    let kind = transition.kind.to_lowercase().replace(['-', '_', ' '], "");
+    match kind.as_str() {
+        "crossdissolve" | "crossfade" => "fade".to_string(),
+        k if XFADE_TRANSITIONS.contains(&k) => k.to_string(),
+        _ => "fade".to_string(),
+    }
+}
+
+fn is_dip(transition: &Transition) -> bool {
+    transition.kind.eq_ignore_ascii_case("dip")
+}
 
+// End of the chain of a clip that belongs to a transition run: timestamps rebased to the
+// start of its handle, missing handle frames frozen, and a common frame rate for xfade.
+// Dips fade the clip to/from the transition color around the cut.
+fn build_transition_video_tail(i: usize, clips: &[Clip], edges: &ClipEdges, pre_src: f64, settings: &ProjectSettings) -> String {
+    let clip = &clips[i];
+    let mut tail = vec!["setpts=PTS-STARTPTS".to_string()];
+
+    let freeze_pre = edges.pre - pre_src;
+    if freeze_pre > 0.0 || edges.post > 0.0 {
+        // The post handle is always padded: xfade drops whatever is left after the transition
+        tail.push(format!(
+            "tpad=start_mode=clone:start_duration={:.4}:stop_mode=clone:stop_duration={:.4}",
+            freeze_pre.max(0.0), edges.post
+        ));
+    }
+    tail.push(format!("fps={},format=yuva420p", settings.fps));
 
-          video_layers.push((i, clip.start, clip.duration));
+    if let Some(t) = clip.transition_in.as_ref().filter(|t| edges.prev.is_some() && is_dip(t)) {
+        let color = ffmpeg_color(t.color.as_deref().unwrap_or("black"));
+        tail.push(format!("fade=t=in:st=0:d={:.4}:color={}", edges.pre, color));
+    }
+    if let Some(next) = edges.next {
+        if let Some(t) = clips[next].transition_in.as_ref().filter(|t| is_dip(t)) {
+            let color = ffmpeg_color(t.color.as_deref().unwrap_or("black"));
+            tail.push(format!(
+                "fade=t=out:st={:.4}:d={:.4}:color={}",
+                edges.pre + clip.duration - edges.post, edges.post, color
+            ));
         }
+    }
 
-        // --- Processamento de Áudio com Keyframes ---
-        if !is_image {
-            let delay_ms = (clip.start * 1000.0).round() as i64;
-            
-            let vol_kfs = clip.keyframes.as_ref()
-                .and_then(|k| k.volume.as_ref())
-                .map(|v| v.as_slice())
-                .unwrap_or(&[]);
+    format!(",{}[cv{}]", tail.join(","), i)
+}
 
-            let volume_expr = build_volume_expression(vol_kfs, clip.mute.unwrap_or(false));
+// Joins the clips of the run starting at `first` with xfade and positions the result on the
+// timeline. Returns the layer as (label, start, end) for the overlay stack.
+fn build_transition_run(first: usize, clips: &[Clip], plan: &[ClipEdges], settings: &ProjectSettings, filters: &mut Vec<String>) -> (String, f64, f64) {
+    let origin = clips[first].start;
+    let mut current = format!("cv{}", first);
+    let mut last = first;
+
+    while let Some(next) = plan[last].next {
+        let transition = clips[next].transition_in.as_ref().unwrap();
+        let duration = plan[next].pre * 2.0;
+        let offset = clips[next].start - plan[next].pre - origin;
+
+        // A dip is a cut in the middle of two fades to the same color
+        let kind = if is_dip(transition) {
+            "custom:expr='if(gt(P,0.5),A,B)'".to_string()
+        } else {
+            xfade_transition_name(transition)
+        };
 
+        let output = format!("xf{}", next);
+        filters.push(format!(
+            "[{}][cv{}]xfade=transition={}:duration={:.4}:offset={:.4}[{}]",
+            current, next, kind, duration, offset.max(0.0), output
+        ));
+        current = output;
+        last = next;
+    }
 
-            println!("--- Comando {} ---", volume_expr);
+    let end = clips[last].start + clips[last].duration;
+    filters.push(format!(
+        "[{}]trim=duration={:.4},setpts=PTS+{}/TB,fps={}[vr{}]",
+        current, end - origin, origin, settings.fps, first
+    ));
 
-            //if volume equal 1 is because there is no keyframes audio
+    (format!("vr{}", first), origin, end)
+}
 
-            if volume_expr == "1"
-            {
+// Audio side of the transitions: after rebasing to the start of the handle, fade in up to
+// the end of the incoming transition and fade out from the start of the outgoing one.
+// Both clips are summed by amix, so the two fades make the crossfade.
+fn build_transition_audio_fades(clip: &Clip, edges: &ClipEdges, pre_src: f64, post_src: f64) -> String {
+    if edges.prev.is_none() && edges.next.is_none() {
+        return String::new();
+    }
 
-                println!("São iguais!");
-                    let delay_ms = (clip.start * 1000.0).round() as i64;
-                    let volume = if clip.mute.unwrap_or(false) { "0" } else { "1" };
-                    
-                    let mut a_effects = Vec::new();
-                    if let Some(fi) = clip.fadeinAudio {
-                        if fi > 0.0 { a_effects.push(format!("afade=t=in:st=0:d={}", fi)); }
-                        
-                        println!("Fade in detect");
-                    }
-                    if let Some(fo) = clip.fadeoutAudio {
-                        if fo > 0.0 {
-                            a_effects.push(format!("afade=t=out:st={}:d={}", clip.duration - fo, fo));
-                        }
-                    }
+    let mut fades = vec!["asetpts=PTS-STARTPTS".to_string()];
+    if edges.prev.is_some() {
+        fades.push(format!("afade=t=in:st=0:d={:.4}", pre_src + edges.pre));
+    }
+    if edges.next.is_some() {
+        fades.push(format!(
+            "afade=t=out:st={:.4}:d={:.4}",
+            pre_src + clip.duration - edges.post, edges.post + post_src
+        ));
+    }
 
-                    let a_effects_str = if a_effects.is_empty() { String::new() } else { format!(",{}", a_effects.join(",")) };
+    format!(",{}", fades.join(","))
+}
 
-                    filters.push(format!(
-                        "[{}:a]atrim=start={}:duration={},asetpts=PTS-STARTPTS,volume={}{},adelay={}|{},aresample=async=1[a{}]",
-                        i, clip.beginmoment, clip.duration, volume, a_effects_str, delay_ms, delay_ms, i
-                    ));
-                    audio_outputs.push(format!("[a{}]", i));
-            }
-            else
-            {
+// Lowest speed accepted from keyframes, avoids divisions by zero in the remap
+const MIN_SPEED: f64 = 0.01;
+
+// Length (in timeline seconds) of each constant-tempo piece used to approximate audio speed ramps
+const SPEED_AUDIO_STEP: f64 = 0.1;
+
+// One piece of the speed curve: from `time` (clip-relative timeline seconds) the speed
+// starts at `speed` and changes linearly by `slope` per second. `source` is the amount of
+// source material already consumed when the piece begins.
+#[derive(Debug, Clone, Copy)]
+struct SpeedSegment {
+    time: f64,
+    source: f64,
+    speed: f64,
+    slope: f64,
+}
 
-                let mut a_filters = Vec::new();
-                 // 1. Primeiro cortamos o áudio original
-                a_filters.push(format!("atrim=start={:.4}:duration={:.4}", clip.beginmoment, clip.duration));
-                
-                // 2. Resetamos o PTS para que o áudio comece em 0 internamente
-                a_filters.push("asetpts=PTS-STARTPTS".to_string());
+impl SpeedSegment {
+    fn source_at(&self, t: f64) -> f64 {
+        let dt = t - self.time;
+        self.source + self.speed * dt + 0.5 * self.slope * dt * dt
+    }
+}
 
-                // 3. Aplicamos o volume (dinâmico por keyframes ou fixo)
-                a_filters.push(format!("volume=eval=frame:volume='{}'", volume_expr));
+fn clip_speed_keyframes(clip: &Clip) -> &[Keyframe] {
+    clip.keyframes.as_ref()
+        .and_then(|k| k.speed.as_ref())
+        .map(|v| v.as_slice())
+        .unwrap_or(&[])
+}
 
-                // 4. Aplicamos o delay para posicionar na timeline
-                a_filters.push(format!("adelay={}|{}", delay_ms, delay_ms));
+// Speed is held before the first and after the last keyframe and follows the curve of each
+// keyframe between them (the timeline preview integrates every curve as linear)
+fn speed_segments(keyframes: &[Keyframe]) -> Vec<SpeedSegment> {
+    let mut sorted = keyframes.to_vec();
+    sorted.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal));
 
-                // 5. Unimos os filtros e definimos a saída [aX]
-                let filter_string = a_filters.join(",");
-                filters.push(format!("[{}:a]{}[a{}]", i, filter_string, i));
-                
-                audio_outputs.push(format!("[a{}]", i));
-            }
+    let mut segments = Vec::new();
+    let mut source = 0.0;
+
+    let first = &sorted[0];
+    if first.time > 0.0 {
+        segments.push(SpeedSegment { time: 0.0, source: 0.0, speed: first.value.max(MIN_SPEED), slope: 0.0 });
+        source = first.time * first.value.max(MIN_SPEED);
+    }
+
+    for pair in sorted.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        let dt = b.time - a.time;
+        if dt <= 0.0 {
+            continue;
+        }
+        // Held keyframes keep their speed until the next one. The inverse of an eased
+        // integral has no closed form for setpts, so eased curves are followed by
+        // BEZIER_SAMPLES linear ramps
+        let steps = match a.interpolation {
+            Interpolation::Linear | Interpolation::Hold => 1,
+            _ => BEZIER_SAMPLES,
+        };
+        let speed_at = |n: usize| {
+            let value = a.value + (b.value - a.value) * eased_progress(a, n as f64 / steps as f64);
+            value.max(MIN_SPEED)
+        };
+        let step = dt / steps as f64;
+        for n in 0..steps {
+            let (va, vb) = (speed_at(n), speed_at(n + 1));
+            segments.push(SpeedSegment { time: a.time + step * n as f64, source, speed: va, slope: (vb - va) / step });
+            source += step * (va + vb) / 2.0;
         }
     }
 
-    // --- Composição Final ---
-    filters.push(format!("color=s=1920x1080:c=black:r=30:d={:.4}[bg]", total_duration));
-    
-    let mut current_v_layer = "bg".to_string();
-    for (idx, (input_idx, start, duration)) in video_layers.iter().enumerate() {
-        let next_v_layer = if idx == video_layers.len() - 1 { "outv_pre".to_string() } else { format!("l{}", idx) };
-        filters.push(format!(
-            "[{}] [v{}] overlay=enable='between(t,{:.4},{:.4})' [ {} ]",
-            current_v_layer, input_idx, start, start + duration, next_v_layer
-        ));
-        current_v_layer = next_v_layer;
+    let last = sorted.last().unwrap();
+    segments.push(SpeedSegment { time: last.time, source, speed: last.value.max(MIN_SPEED), slope: 0.0 });
+
+    segments
+}
+
+// Source seconds consumed after `t` seconds of the clip have played on the timeline
+fn source_time_at(segments: &[SpeedSegment], t: f64) -> f64 {
+    let segment = segments.iter()
+        .rev()
+        .find(|s| s.time <= t)
+        .unwrap_or(&segments[0]);
+    segment.source_at(t)
+}
+
+// How much of the source file (after beginmoment) the clip uses
+fn clip_source_duration(clip: &Clip) -> f64 {
+    let speed_kfs = clip_speed_keyframes(clip);
+    if speed_kfs.is_empty() {
+        return clip.duration;
     }
-    
-    filters.push(format!("[{}]format=yuv420p[outv]", if video_layers.is_empty() { "bg" } else { "outv_pre" }));
+    source_time_at(&speed_segments(speed_kfs), clip.duration)
+}
 
-    if audio_outputs.is_empty() {
-        filters.push(format!("anullsrc=r=44100:cl=stereo:d={:.4}[outa]", total_duration));
-    } else {
-        filters.push(format!(
-            "{}amix=inputs={}:duration=longest:dropout_transition=99999[outa]",
-            audio_outputs.join(""),
-            audio_outputs.len()
+// Inverse of the speed integral as an ffmpeg expression of T (source seconds after the trim),
+// giving the timeline time at which each source frame must be shown
+fn build_speed_pts_expression(keyframes: &[Keyframe]) -> String {
+    let segments = speed_segments(keyframes);
+
+    let inverse = |s: &SpeedSegment| {
+        if s.slope.abs() < 1e-9 {
+            format!("{:.6}+(T-{:.6})/{:.6}", s.time, s.source, s.speed)
+        } else {
+            // Solve source + speed*dt + slope/2*dt^2 = T for dt
+            format!(
+                "{:.6}+(sqrt(max(0,{:.6}*{:.6}+2*({:.6})*(T-{:.6})))-{:.6})/({:.6})",
+                s.time, s.speed, s.speed, s.slope, s.source, s.speed, s.slope
+            )
+        }
+    };
+
+    let mut expr = String::new();
+    for pair in segments.windows(2) {
+        expr.push_str(&format!("if(lt(T,{:.6}),{},", pair[1].source, inverse(&pair[0])));
+    }
+    expr.push_str(&inverse(segments.last().unwrap()));
+    for _ in 1..segments.len() {
+        expr.push(')');
+    }
+
+    expr
+}
+
+// atempo only accepts factors between 0.5 and 100, slower speeds are chained
+fn build_atempo_chain(tempo: f64) -> String {
+    let mut tempo = tempo.clamp(MIN_SPEED, 100.0);
+    let mut chain = Vec::new();
+    while tempo < 0.5 {
+        chain.push("atempo=0.5".to_string());
+        tempo /= 0.5;
+    }
+    chain.push(format!("atempo={:.6}", tempo));
+    chain.join(",")
+}
+
+// Splits the clip's timeline duration into pieces of constant tempo. Each entry holds the
+// source length of the piece and the tempo it must be played at (atempo keeps the pitch).
+fn speed_audio_pieces(keyframes: &[Keyframe], duration: f64) -> Vec<(f64, f64)> {
+    let segments = speed_segments(keyframes);
+    let mut cuts = vec![0.0];
+
+    for (idx, segment) in segments.iter().enumerate() {
+        let seg_start = segment.time.max(0.0);
+        let seg_end = segments.get(idx + 1).map(|s| s.time).unwrap_or(duration).min(duration);
+        if seg_end <= seg_start {
+            continue;
+        }
+        let steps = if segment.slope == 0.0 {
+            1
+        } else {
+            ((seg_end - seg_start) / SPEED_AUDIO_STEP).ceil().max(1.0) as usize
+        };
+        for step in 1..=steps {
+            cuts.push(seg_start + (seg_end - seg_start) * step as f64 / steps as f64);
+        }
+    }
+    if *cuts.last().unwrap() < duration {
+        cuts.push(duration);
+    }
+
+    cuts.windows(2)
+        .filter(|w| w[1] - w[0] > 1e-6)
+        .map(|w| {
+            let length = source_time_at(&segments, w[1]) - source_time_at(&segments, w[0]);
+            (length, length / (w[1] - w[0]))
+        })
+        .collect()
+}
+
+// Channels picked from the audio stream, as a stereo pan: one channel goes to both sides,
+// two become left and right, more are mixed evenly into both
+fn build_channel_selection(channels: &[u32]) -> Option<String> {
+    match channels {
+        [] => None,
+        [only] => Some(format!("pan=stereo|c0=c{}|c1=c{}", only, only)),
+        [left, right] => Some(format!("pan=stereo|c0=c{}|c1=c{}", left, right)),
+        all => {
+            let gain = 1.0 / all.len() as f64;
+            let sum = all.iter().map(|c| format!("{:.4}*c{}", gain, c)).collect::<Vec<_>>().join("+");
+            Some(format!("pan=stereo|c0={}|c1={}", sum, sum))
+        }
+    }
+}
+
+// Trimmed audio of clip `i` with timestamps starting at zero (`pre` seconds of handle before
+// it, `post` after it). With speed keyframes, which never get handles, the source
+// is cut into constant-tempo pieces that are stretched with atempo and concatenated again.
+// The returned chain has no output label so callers can keep appending filters to it.
+fn build_audio_source(i: usize, clip: &Clip, pre: f64, post: f64) -> String {
+    // Handles are kept at negative timestamps so the clip itself still starts at zero
+    let shift = if pre > 0.0 { format!("-{:.4}/TB", pre) } else { String::new() };
+    let channels = clip
+        .audio_channels
+        .as_deref()
+        .and_then(build_channel_selection)
+        .map(|pan| format!(",{}", pan))
+        .unwrap_or_default();
+    let trim = format!(
+        "[{}:a:{}]atrim=start={:.4}:duration={:.4},asetpts=PTS-STARTPTS{}{}",
+        i, clip.audio_stream.unwrap_or(0), clip.beginmoment - pre, clip_source_duration(clip) + pre + post, shift, channels
+    );
+
+    let speed_kfs = clip_speed_keyframes(clip);
+    if speed_kfs.is_empty() {
+        return trim;
+    }
+
+    let pieces = speed_audio_pieces(speed_kfs, clip.duration);
+    if pieces.len() <= 1 {
+        let tempo = pieces.first().map(|p| p.1).unwrap_or(1.0);
+        return format!("{},{}", trim, build_atempo_chain(tempo));
+    }
+
+    let mut timestamps = Vec::new();
+    let mut consumed = 0.0;
+    for (length, _) in &pieces[..pieces.len() - 1] {
+        consumed += length;
+        timestamps.push(format!("{:.6}", consumed));
+    }
+
+    let segment_labels: String = (0..pieces.len()).map(|p| format!("[sa{}_{}]", i, p)).collect();
+    let mut chains = vec![format!("{},asegment=timestamps={}{}", trim, timestamps.join("|"), segment_labels)];
+
+    for (p, (_, tempo)) in pieces.iter().enumerate() {
+        chains.push(format!(
+            "[sa{}_{}]asetpts=PTS-STARTPTS,{}[st{}_{}]",
+            i, p, build_atempo_chain(*tempo), i, p
         ));
     }
 
-    filters.join(";")
+    let tempo_labels: String = (0..pieces.len()).map(|p| format!("[st{}_{}]", i, p)).collect();
+    chains.push(format!("{}concat=n={}:v=0:a=1", tempo_labels, pieces.len()));
+
+    chains.join(";")
+}
+
+fn db_to_linear(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
 }
 
+// Effect chain of a clip (starting with a comma, empty without effects). The audio is
+// resampled to `sample_rate` first. Values are clamped to the ranges accepted by each filter.
+fn build_audio_effects(clip: &Clip, sample_rate: u32) -> String {
+    let effects = match &clip.audio_effects {
+        Some(effects) => effects,
+        None => return String::new(),
+    };
 
+    let mut filters = Vec::new();
+    for effect in effects.iter().filter(|e| e.enabled.unwrap_or(true)) {
+        match &effect.kind {
+            AudioEffectKind::Eq { bands } => {
+                for band in bands {
+                    let q = band.q.unwrap_or(1.0).max(0.01);
+                    let f = band.frequency.clamp(10.0, sample_rate as f64 / 2.0 - 1.0);
+                    filters.push(match band.kind.as_deref().unwrap_or("peak") {
+                        "lowshelf" => format!("lowshelf=f={:.1}:t=q:w={:.3}:g={:.2}", f, q, band.gain),
+                        "highshelf" => format!("highshelf=f={:.1}:t=q:w={:.3}:g={:.2}", f, q, band.gain),
+                        "lowpass" => format!("lowpass=f={:.1}:t=q:w={:.3}", f, q),
+                        "highpass" => format!("highpass=f={:.1}:t=q:w={:.3}", f, q),
+                        _ => format!("equalizer=f={:.1}:t=q:w={:.3}:g={:.2}", f, q, band.gain),
+                    });
+                }
+            }
+            AudioEffectKind::Compressor { threshold, ratio, attack, release, makeup, knee } => {
+                filters.push(format!(
+                    "acompressor=threshold={:.6}:ratio={:.2}:attack={:.2}:release={:.2}:makeup={:.4}:knee={:.4}",
+                    db_to_linear(threshold.unwrap_or(-18.0)).clamp(0.000976563, 1.0),
+                    ratio.unwrap_or(4.0).clamp(1.0, 20.0),
+                    attack.unwrap_or(20.0).clamp(0.01, 2000.0),
+                    release.unwrap_or(250.0).clamp(0.01, 9000.0),
+                    db_to_linear(makeup.unwrap_or(0.0)).clamp(1.0, 64.0),
+                    db_to_linear(knee.unwrap_or(9.0)).clamp(1.0, 8.0),
+                ));
+            }
+            AudioEffectKind::Gate { threshold, ratio, attack, release, range } => {
+                filters.push(format!(
+                    "agate=threshold={:.6}:ratio={:.2}:attack={:.2}:release={:.2}:range={:.6}",
+                    db_to_linear(threshold.unwrap_or(-40.0)).clamp(0.0, 1.0),
+                    ratio.unwrap_or(2.0).clamp(1.0, 9000.0),
+                    attack.unwrap_or(20.0).clamp(0.01, 9000.0),
+                    release.unwrap_or(250.0).clamp(0.01, 9000.0),
+                    db_to_linear(range.unwrap_or(-24.0)).clamp(0.0, 1.0),
+                ));
+            }
+            AudioEffectKind::Limiter { ceiling, attack, release } => {
+                // level=0 keeps alimiter from raising the output to the ceiling
+                filters.push(format!(
+                    "alimiter=limit={:.6}:attack={:.2}:release={:.2}:level=0",
+                    db_to_linear(ceiling.unwrap_or(-1.0)).clamp(0.0625, 1.0),
+                    attack.unwrap_or(5.0).clamp(0.1, 80.0),
+                    release.unwrap_or(50.0).clamp(1.0, 8000.0),
+                ));
+            }
+            AudioEffectKind::Deesser { intensity, max_reduction, keep } => {
+                filters.push(format!(
+                    "deesser=i={:.3}:m={:.3}:f={:.3}",
+                    intensity.unwrap_or(0.5).clamp(0.0, 1.0),
+                    max_reduction.unwrap_or(0.5).clamp(0.0, 1.0),
+                    keep.unwrap_or(0.5).clamp(0.0, 1.0),
+                ));
+            }
+        }
+    }
 
+    if filters.is_empty() {
+        String::new()
+    } else {
+        // The source may be at any rate; the EQ limits above are for the project rate
+        format!(",aresample={},{}", sample_rate, filters.join(","))
+    }
+}
 
 fn build_volume_expression(keyframes: &[Keyframe], mute: bool) -> String {
     // 1. Se estiver mutado, volume é zero absoluto
@@ -597,7 +3242,7 @@ fn build_volume_expression(keyframes: &[Keyframe], mute: bool) -> String {
     expr.push_str(&format!("if(lt(t,{:.4}),pow(10,({:.4})/20)", sorted[0].time, first_db));
     open_parents += 1;
 
-    // 5. Interpolação Linear entre os pontos
+    // 5. Interpolação entre os pontos (linear ou com a curva do keyframe)
     for i in 0..sorted.len() - 1 {
         let p1 = &sorted[i];
         let p2 = &sorted[i + 1];
@@ -605,10 +3250,15 @@ fn build_volume_expression(keyframes: &[Keyframe], mute: bool) -> String {
         let db2 = (p2.value * 100.0) - 50.0;
 
         // Fórmula: db1 + (db2 - db1) * (t - t1) / (t2 - t1)
-        let lerp_db = format!(
-            "(({:.4})+(({:.4})-({:.4}))*(t-({:.4}))/(({:.4})-({:.4})))",
-            db1, db2, db1, p1.time, p2.time, p1.time
-        );
+        let lerp_db = if p1.interpolation == Interpolation::Linear || p2.time == p1.time {
+            format!(
+                "(({:.4})+(({:.4})-({:.4}))*(t-({:.4}))/(({:.4})-({:.4})))",
+                db1, db2, db1, p1.time, p2.time, p1.time
+            )
+        } else {
+            let progress = format!("((t-({:.4}))/({:.4}))", p1.time, p2.time - p1.time);
+            format!("(({:.4})+(({:.4})-({:.4}))*{})", db1, db2, db1, build_easing_expression(p1, &progress))
+        };
 
         expr.push_str(&format!(",if(between(t,{:.4},{:.4}),pow(10,{}/20)", p1.time, p2.time, lerp_db));
         open_parents += 1;
@@ -620,7 +3270,7 @@ fn build_volume_expression(keyframes: &[Keyframe], mute: bool) -> String {
 
     // 7. Fechar todos os parênteses dos 'if's abertos
     for _ in 0..open_parents {
-        expr.push_str(")");
+        expr.push(')');
     }
 
     expr
@@ -634,12 +3284,19 @@ fn build_volume_expression(keyframes: &[Keyframe], mute: bool) -> String {
 pub fn build_opacity_expression(keyframes: &[Keyframe]) -> String {
     if keyframes.is_empty() { return "1".to_string(); }
 
+    build_interpolation_expression(keyframes, "t")
+}
+
+// Linear interpolation between keyframes as an ffmpeg expression of `var`,
+// holding the first value before the first keyframe and the last one after the end
+fn build_interpolation_expression(keyframes: &[Keyframe], var: &str) -> String {
+
     // Criamos uma cópia local para ordenar sem "roubar" o Vec do usuário
     let mut sorted_keys = keyframes.to_vec(); 
     sorted_keys.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
 
     // 2. Função recursiva para montar os ifs aninhados
-    fn build_expression(keys: &[Keyframe], index: usize) -> String {
+    fn build_expression(keys: &[Keyframe], index: usize, var: &str) -> String {
         let current = &keys[index];
 
         // Se for o último keyframe, mantemos o valor dele para o resto do vídeo
@@ -647,44 +3304,528 @@ pub fn build_opacity_expression(keyframes: &[Keyframe]) -> String {
             return format!("{:.3}", current.value);
         }
 
-        let next = &keys[index + 1];
-        
-        // Cálculo da interpolação linear entre o ponto atual e o próximo:
-        // Valor = ValorInicial + (TempoAtual - TempoInicial) * (VariaçãoValor / VariaçãoTempo)
-        let delta_val = next.value - current.value;
-        let delta_time = next.time - current.time;
-        
-        let lerp = if delta_time == 0.0 {
-            format!("{:.3}", next.value)
-        } else {
-            format!("{:.3}+(t-{:.3})*({:.3}/{:.3})", current.value, current.time, delta_val, delta_time)
+        let next = &keys[index + 1];
+        
+        // Cálculo da interpolação linear entre o ponto atual e o próximo:
+        // Valor = ValorInicial + (TempoAtual - TempoInicial) * (VariaçãoValor / VariaçãoTempo)
+        let delta_val = next.value - current.value;
+        let delta_time = next.time - current.time;
+        
+        let lerp = if delta_time == 0.0 {
+            format!("{:.3}", next.value)
+        } else if current.interpolation == Interpolation::Linear {
+            format!("{:.3}+({}-{:.3})*({:.3}/{:.3})", current.value, var, current.time, delta_val, delta_time)
+        } else {
+            let progress = format!("(({}-{:.3})/{:.3})", var, current.time, delta_time);
+            format!("{:.3}+({:.3})*{}", current.value, delta_val, build_easing_expression(current, &progress))
+        };
+
+        // if(t < tempo_do_proximo, interpola, recursão_para_proximos)
+        format!("if(lt({},{:.3}),{},{})", var, next.time, lerp, build_expression(keys, index + 1, var))
+    }
+
+    // Se o primeiro keyframe não começar em 0s, definimos o valor inicial até lá
+    if sorted_keys[0].time > 0.0 {
+        format!("if(lt({},{:.3}),{:.3},{})", 
+            var,
+            sorted_keys[0].time, 
+            sorted_keys[0].value, 
+            build_expression(&sorted_keys, 0, var)
+        )
+    } else {
+        build_expression(&sorted_keys, 0, var)
+    }
+}
+
+
+// Number of linear pieces used to follow a bezier curve
+const BEZIER_SAMPLES: usize = 16;
+
+// Eased progress (0..1, bezier may overshoot) of the segment that starts at `keyframe`,
+// as an expression of `progress`, the linear 0..1 position inside the segment
+fn build_easing_expression(keyframe: &Keyframe, progress: &str) -> String {
+    let p = progress;
+    match keyframe.interpolation {
+        Interpolation::Linear => p.to_string(),
+        Interpolation::Hold => "0".to_string(),
+        Interpolation::EaseIn => format!("({p}*{p})", p = p),
+        Interpolation::EaseOut => format!("(1-(1-{p})*(1-{p}))", p = p),
+        Interpolation::EaseInOut => format!("({p}*{p}*(3-2*{p}))", p = p),
+        Interpolation::Bezier => {
+            let mut expr = String::new();
+            for pair in bezier_easing_points(keyframe).windows(2) {
+                let ((xa, ya), (xb, yb)) = (pair[0], pair[1]);
+                if xb - xa <= 1e-9 {
+                    continue;
+                }
+                expr.push_str(&format!(
+                    "if(lt({p},{xb:.4}),{ya:.4}+({p}-{xa:.4})*{slope:.4},",
+                    p = p, xb = xb, ya = ya, xa = xa, slope = (yb - ya) / (xb - xa)
+                ));
+            }
+            let closing = expr.matches("if(").count();
+            format!("({}1{})", expr, ")".repeat(closing))
+        }
+    }
+}
+
+// The bezier curve of a keyframe sampled as (x, y) points. The curve is given as x(s), y(s),
+// between the samples it is followed linearly in x.
+fn bezier_easing_points(keyframe: &Keyframe) -> Vec<(f64, f64)> {
+    let [x1, y1, x2, y2] = keyframe.bezier.unwrap_or([0.42, 0.0, 0.58, 1.0]);
+    let bezier = |a: f64, b: f64, s: f64| 3.0 * a * s * (1.0 - s).powi(2) + 3.0 * b * s * s * (1.0 - s) + s.powi(3);
+    (0..=BEZIER_SAMPLES)
+        .map(|n| n as f64 / BEZIER_SAMPLES as f64)
+        .map(|s| (bezier(x1.clamp(0.0, 1.0), x2.clamp(0.0, 1.0), s), bezier(y1, y2, s)))
+        .collect()
+}
+
+// Value of build_easing_expression for a known `progress`, for the curves Rust evaluates
+// itself (baked keyframes, speed integration)
+fn eased_progress(keyframe: &Keyframe, progress: f64) -> f64 {
+    let p = progress.clamp(0.0, 1.0);
+    match keyframe.interpolation {
+        Interpolation::Linear => p,
+        Interpolation::Hold => 0.0,
+        Interpolation::EaseIn => p * p,
+        Interpolation::EaseOut => 1.0 - (1.0 - p) * (1.0 - p),
+        Interpolation::EaseInOut => p * p * (3.0 - 2.0 * p),
+        Interpolation::Bezier => bezier_easing_points(keyframe)
+            .windows(2)
+            .find(|pair| pair[1].0 - pair[0].0 > 1e-9 && p < pair[1].0)
+            .map(|pair| {
+                let ((xa, ya), (xb, yb)) = (pair[0], pair[1]);
+                ya + (p - xa) * (yb - ya) / (xb - xa)
+            })
+            .unwrap_or(1.0),
+    }
+}
+
+// Focal length (in pixels) of the virtual camera used for 3D rotation, same as the preview
+const ROTATION3D_FOCAL_LENGTH: f64 = 1200.0;
+
+// Transparent border kept around the layer while warping, so the perspective
+// filter clamps to transparent pixels instead of smearing the clip edges
+const TRANSFORM_MARGIN: u32 = 2;
+
+// Expression of one transform property: its keyframes when it has any, otherwise the
+// static value (or the default)
+fn transform_value(value: Option<f64>, keyframes: Option<&Vec<Keyframe>>, default: f64, var: &str) -> String {
+    match keyframes {
+        Some(kfs) if !kfs.is_empty() => format!("({})", build_interpolation_expression(kfs, var)),
+        _ => format!("{:.4}", value.unwrap_or(default)),
+    }
+}
+
+// Numeric counterpart of transform_value, for the values sent with build_keyframe_sendcmd
+fn track_value_at(value: Option<f64>, keyframes: Option<&Vec<Keyframe>>, default: f64, t: f64) -> f64 {
+    match keyframes {
+        Some(kfs) if !kfs.is_empty() => keyframe_value_at(kfs, t, default),
+        _ => value.unwrap_or(default),
+    }
+}
+
+// Animates filter options that do not take time expressions (and would otherwise need a
+// per-pixel geq): `commands(t)` gives the (instance, option, value) triples at frame time
+// `t`, sampled once per frame between `start` and `end` and sent by sendcmd when the value
+// changes. The filters start with the values at `start`, the last ones are held after `end`.
+// Targets are `filter@name` instances, so each name must be unique in the graph. Values go
+// through sendcmd's own parser and must not contain spaces, commas or semicolons.
+fn build_keyframe_sendcmd<F>(start: f64, end: f64, fps: f64, commands: F) -> Option<String>
+where
+    F: Fn(f64) -> Vec<(String, &'static str, String)>,
+{
+    let frames = ((end - start) * fps).ceil().max(0.0) as usize;
+    let mut current = commands(start);
+    let mut intervals = Vec::new();
+    for n in 1..=frames {
+        let t = start + n as f64 / fps;
+        let next = commands(t);
+        let changed: Vec<String> = next.iter()
+            .filter(|command| !current.contains(command))
+            .map(|(target, option, value)| format!("{} {} {}", target, option, value))
+            .collect();
+        if !changed.is_empty() {
+            intervals.push(format!("{:.4} {}", t, changed.join(",")));
+        }
+        current = next;
+    }
+
+    if intervals.is_empty() {
+        None
+    } else {
+        Some(format!("sendcmd=c='{}'", intervals.join(";")))
+    }
+}
+
+// Keyframed opacity as a per-frame alpha multiply: colorchannelmixer scales the alpha plane
+// and sendcmd updates its factor. `index` names the instance, `start`/`end` is the frame
+// time range of the layer.
+fn build_opacity_filter(index: usize, keyframes: &[Keyframe], start: f64, end: f64, fps: f64) -> String {
+    let target = format!("colorchannelmixer@opacity{}", index);
+    let alpha = |t: f64| format!("{:.3}", keyframe_value_at(keyframes, t, 1.0).clamp(0.0, 1.0));
+    let mixer = format!("{}=aa={}", target, alpha(start));
+    match build_keyframe_sendcmd(start, end, fps, |t| vec![(target.clone(), "aa", alpha(t))]) {
+        Some(sendcmd) => format!("format=rgba,{},{}", sendcmd, mixer),
+        None => format!("format=rgba,{}", mixer),
+    }
+}
+
+// Size of the clip once fitted (centered, aspect kept) in the canvas. Without the asset
+// dimensions the clip is assumed to fill the canvas.
+fn fitted_clip_size(clip: &Clip, settings: &ProjectSettings) -> (f64, f64) {
+    let (w, h) = (settings.width as f64, settings.height as f64);
+    match &clip.dimensions {
+        Some(d) if d.x > 0.0 && d.y > 0.0 => {
+            let fit = (w / d.x).min(h / d.y);
+            (d.x * fit, d.y * fit)
+        }
+        _ => (w, h),
+    }
+}
+
+// Largest share of the clip two opposite crop edges may hide together
+const MAX_CROP: f64 = 0.99;
+
+// Hides the cropped edges of the fitted clip while keeping its size, so anchor and
+// position do not move. Static crops use crop+pad, keyframed ones four transparent drawbox
+// strips resized frame by frame.
+fn build_crop_filter(clip: &Clip, index: usize, settings: &ProjectSettings, time_offset: f64) -> Option<String> {
+    let transform = clip.transform.clone().unwrap_or_default();
+    let kfs = clip.keyframes.as_ref();
+    let edges = [
+        (transform.crop_left, kfs.and_then(|k| k.crop_left.as_ref())),
+        (transform.crop_right, kfs.and_then(|k| k.crop_right.as_ref())),
+        (transform.crop_top, kfs.and_then(|k| k.crop_top.as_ref())),
+        (transform.crop_bottom, kfs.and_then(|k| k.crop_bottom.as_ref())),
+    ];
+    // Opposite edges that meet are scaled back to leave 1% of the clip visible
+    let limit = |[mut l, mut r, mut t, mut b]: [f64; 4]| {
+        if l + r > MAX_CROP {
+            (l, r) = (l * MAX_CROP / (l + r), r * MAX_CROP / (l + r));
+        }
+        if t + b > MAX_CROP {
+            (t, b) = (t * MAX_CROP / (t + b), b * MAX_CROP / (t + b));
+        }
+        [l, r, t, b]
+    };
+
+    let keyframed = edges.iter().any(|(_, k)| k.map(|k| !k.is_empty()).unwrap_or(false));
+    if keyframed {
+        // Each strip starts one pixel outside the frame so an uncropped edge draws nothing,
+        // drawbox reads a zero size as the whole frame
+        let strips = |time: f64| {
+            let [l, r, t, b] = limit(edges.map(|(value, k)| track_value_at(value, k, 0.0, time).clamp(0.0, 0.99)));
+            vec![
+                (format!("drawbox@crop{}l", index), "w", format!("iw*{:.4}+1", l)),
+                (format!("drawbox@crop{}r", index), "x", format!("iw*{:.4}", 1.0 - r)),
+                (format!("drawbox@crop{}t", index), "h", format!("ih*{:.4}+1", t)),
+                (format!("drawbox@crop{}b", index), "y", format!("ih*{:.4}", 1.0 - b)),
+            ]
+        };
+        let initial = strips(-time_offset);
+        let boxes = format!(
+            "{l}=x=-1:y=0:w={lw}:h=ih:{fill},{r}=x={rx}:y=0:w=iw:h=ih:{fill},{t}=x=0:y=-1:w=iw:h={th}:{fill},{b}=x=0:y={by}:w=iw:h=ih:{fill}",
+            l = initial[0].0, lw = initial[0].2, r = initial[1].0, rx = initial[1].2,
+            t = initial[2].0, th = initial[2].2, b = initial[3].0, by = initial[3].2,
+            fill = "color=black@0:t=fill:replace=1"
+        );
+        return Some(match build_keyframe_sendcmd(-time_offset, clip.duration, settings.fps as f64, strips) {
+            Some(sendcmd) => format!("{},{}", sendcmd, boxes),
+            None => boxes,
+        });
+    }
+
+    let [l, r, t, b] = limit(edges.map(|(value, _)| value.unwrap_or(0.0).clamp(0.0, 0.99)));
+    if l + r + t + b <= 0.0 {
+        return None;
+    }
+    Some(format!(
+        "crop=iw*{kw:.4}:ih*{kh:.4}:iw*{l:.4}:ih*{t:.4},pad=iw/{kw:.4}:ih/{kh:.4}:ow*{l:.4}:oh*{t:.4}:color=black@0",
+        kw = 1.0 - l - r, kh = 1.0 - t - b, l = l, t = t
+    ))
+}
+
+// Places the clip on the canvas when it has a transform or 3D rotation. The clip is fitted
+// and centered on a transparent canvas, then `perspective` moves the four corners of that
+// plane: scaled around the anchor, rotated by X, Y and Z (3D rotation plus the 2D one),
+// projected and moved to the anchor position. Returns None for the plain centered fit.
+// perspective has no `t`, so the time comes from the frame count after a fixed `fps`.
+fn build_transform_filter(clip: &Clip, index: usize, settings: &ProjectSettings, time_offset: f64) -> Option<String> {
+    let kfs = clip.keyframes.as_ref();
+    let rotation_kfs = kfs.and_then(|k| k.rotation3d.as_ref()).map(|v| v.as_slice()).unwrap_or(&[]);
+    let zoom_kfs = kfs.and_then(|k| k.zoom.as_ref()).filter(|v| !v.is_empty());
+    let position_kfs = kfs.and_then(|k| k.position.as_ref()).filter(|v| !v.is_empty());
+    let has_transform_kfs = kfs.map(|k| {
+        [&k.position_x, &k.position_y, &k.scale, &k.scale_x, &k.scale_y, &k.rotation]
+            .iter()
+            .any(|track| track.as_ref().map(|v| !v.is_empty()).unwrap_or(false))
+    }).unwrap_or(false) || zoom_kfs.is_some() || position_kfs.is_some();
+
+    let crop_filter = build_crop_filter(clip, index, settings, time_offset);
+    if clip.transform.is_none() && rotation_kfs.is_empty() && !has_transform_kfs && crop_filter.is_none() {
+        return None;
+    }
+
+    let transform = clip.transform.clone().unwrap_or_default();
+    let (w, h) = (settings.width, settings.height);
+    let (pw, ph) = (w + 2 * TRANSFORM_MARGIN, h + 2 * TRANSFORM_MARGIN);
+    let (cw, ch) = fitted_clip_size(clip, settings);
+    let m = TRANSFORM_MARGIN as f64;
+
+    // The first frame is `time_offset` seconds before the clip start when it has a transition handle
+    let time = format!("(in/{}-{:.4})", settings.fps, time_offset);
+
+    let axis = |get: fn(&Rotation3d) -> f64| -> String {
+        if rotation_kfs.is_empty() {
+            return "0".to_string();
+        }
+        let kfs: Vec<Keyframe> = rotation_kfs.iter()
+            .map(|k| Keyframe {
+                id: k.id.clone(),
+                time: k.time,
+                value: get(&k.value),
+                interpolation: k.interpolation,
+                bezier: k.bezier,
+            })
+            .collect();
+        format!("({})", build_interpolation_expression(&kfs, &time))
+    };
+
+    // Anchor relative to the plane center, and its default position (where the centered fit puts it)
+    let (anchor_x, anchor_y) = (transform.anchor_x.unwrap_or(0.5), transform.anchor_y.unwrap_or(0.5));
+    let (ax, ay) = ((anchor_x - 0.5) * cw, (anchor_y - 0.5) * ch);
+
+    // The timeline sizes the clip as the asset times `zoom` (the fit is undone) and places
+    // its top-left corner at `position`, which defaults to the canvas origin
+    let native = clip.dimensions.as_ref().filter(|d| d.x > 0.0 && d.y > 0.0).map(|d| (d.x, d.y)).unwrap_or((cw, ch));
+    let zoom = zoom_kfs.map(|kfs| format!("({})", build_interpolation_expression(kfs, &time)));
+    let scale_kfs = kfs.and_then(|k| k.scale.as_ref()).filter(|v| !v.is_empty());
+    let scale = match (&zoom, scale_kfs) {
+        (Some(zoom), None) => format!("{}*{:.6}", zoom, native.0 / cw),
+        _ => transform_value(transform.scale, scale_kfs, 1.0, &time),
+    };
+    let timeline_position = |get: fn(&Dimensions) -> f64, size: f64, anchor: f64| -> String {
+        let corner = match position_kfs {
+            Some(kfs) => {
+                let kfs: Vec<Keyframe> = kfs.iter()
+                    .map(|k| Keyframe {
+                        id: k.id.clone(),
+                        time: k.time,
+                        value: get(&k.value),
+                        interpolation: k.interpolation,
+                        bezier: k.bezier,
+                    })
+                    .collect();
+                format!("({})", build_interpolation_expression(&kfs, &time))
+            }
+            None => "0".to_string(),
+        };
+        format!("({}+{:.4}*{}*{:.4})", corner, size, zoom.as_deref().unwrap_or("1"), anchor)
+    };
+    let uses_timeline_position = position_kfs.is_some() || (zoom.is_some() && transform.x.is_none() && transform.y.is_none());
+    let position_x_kfs = kfs.and_then(|k| k.position_x.as_ref()).filter(|v| !v.is_empty());
+    let position_y_kfs = kfs.and_then(|k| k.position_y.as_ref()).filter(|v| !v.is_empty());
+    let x = match position_x_kfs {
+        None if uses_timeline_position => timeline_position(|d| d.x, native.0, anchor_x),
+        _ => transform_value(transform.x, position_x_kfs, w as f64 / 2.0 + ax, &time),
+    };
+    let y = match position_y_kfs {
+        None if uses_timeline_position => timeline_position(|d| d.y, native.1, anchor_y),
+        _ => transform_value(transform.y, position_y_kfs, h as f64 / 2.0 + ay, &time),
+    };
+    let vars = [
+        format!("{}*PI/180", axis(|r| r.x)),
+        format!("{}*PI/180", axis(|r| r.y)),
+        format!("({}+{})*PI/180", axis(|r| r.z), transform_value(transform.rotation, kfs.and_then(|k| k.rotation.as_ref()), 0.0, &time)),
+        format!("{}*{}", scale, transform_value(transform.scale_x, kfs.and_then(|k| k.scale_x.as_ref()), 1.0, &time)),
+        format!("{}*{}", scale, transform_value(transform.scale_y, kfs.and_then(|k| k.scale_y.as_ref()), 1.0, &time)),
+        format!("{}+{:.4}", x, m),
+        format!("{}+{:.4}", y, m),
+    ];
+    // ld(0..2) angles, ld(6)/ld(7) scale, ld(8)/ld(9) anchor position in the padded frame
+    let setup = format!(
+        "st(0,{});st(1,{});st(2,{});st(6,{});st(7,{});st(8,{});st(9,{})",
+        vars[0], vars[1], vars[2], vars[3], vars[4], vars[5], vars[6]
+    );
+
+    let f = ROTATION3D_FOCAL_LENGTH;
+    let corner = |px: f64, py: f64| {
+        // Scaled point relative to the anchor
+        let (qx, qy) = (format!("({:.3})*ld(6)", px - ax), format!("({:.3})*ld(7)", py - ay));
+        // X then Y rotation, keeping x in ld(3), y in ld(4) and depth in ld(5)
+        let rotated = format!(
+            "{setup};st(3,{qx}*cos(ld(1))+{qy}*sin(ld(0))*sin(ld(1)));st(4,{qy}*cos(ld(0)));st(5,-{qx}*sin(ld(1))+{qy}*sin(ld(0))*cos(ld(1)))",
+            setup = setup, qx = qx, qy = qy
+        );
+        let project = format!("{f:.1}/max(1,{f:.1}+ld(5))", f = f);
+        (
+            format!("{};ld(8)+(ld(3)*cos(ld(2))-ld(4)*sin(ld(2)))*{}", rotated, project),
+            format!("{};ld(9)+(ld(3)*sin(ld(2))+ld(4)*cos(ld(2)))*{}", rotated, project),
+        )
+    };
+
+    // Corner order expected by perspective: top-left, top-right, bottom-left, bottom-right
+    let (hx, hy) = (pw as f64 / 2.0, ph as f64 / 2.0);
+    let corners = [corner(-hx, -hy), corner(hx, -hy), corner(-hx, hy), corner(hx, hy)];
+    let coords: Vec<String> = corners.iter()
+        .enumerate()
+        .map(|(n, (x, y))| format!("x{n}='{x}':y{n}='{y}'", n = n, x = x, y = y))
+        .collect();
+
+    let crop = crop_filter.map(|c| format!(",{}", c)).unwrap_or_default();
+
+    Some(format!(
+        "scale={w}:{h}:force_original_aspect_ratio=decrease,format=yuva444p{crop},pad={pw}:{ph}:(ow-iw)/2:(oh-ih)/2:color=black@0,fps={fps},perspective={coords}:sense=destination:eval=frame,crop={w}:{h}:{m}:{m}",
+        w = w, h = h, crop = crop, pw = pw, ph = ph, fps = settings.fps, coords = coords.join(":"), m = TRANSFORM_MARGIN
+    ))
+}
+
+
+// chromakey gives the clip an alpha channel, kept by the rest of the chain so the overlay
+// shows the lower tracks through it. despill works on the dominant channel of the key color.
+fn build_chroma_key_filter(clip: &Clip) -> Option<String> {
+    let key = clip.chroma_key.as_ref().filter(|k| k.enabled.unwrap_or(true))?;
+    let color = ffmpeg_color(&key.color);
+    let mut filter = format!(
+        "chromakey=color={}:similarity={:.4}:blend={:.4}",
+        color,
+        key.similarity.unwrap_or(0.1).clamp(0.01, 1.0),
+        key.blend.unwrap_or(0.0).clamp(0.0, 1.0)
+    );
+
+    if let Some(spill) = key.spill.filter(|s| *s > 0.0) {
+        let hex = color.trim_start_matches("0x");
+        let channel = |range: std::ops::Range<usize>| hex.get(range).and_then(|c| u8::from_str_radix(c, 16).ok()).unwrap_or(0);
+        let spill_type = if channel(4..6) > channel(2..4) { "blue" } else { "green" };
+        filter.push_str(&format!(",format=rgba,despill=type={}:mix={:.4}", spill_type, spill.clamp(0.0, 1.0)));
+    }
+
+    Some(filter)
+}
+
+// Color correction chain of a clip, None when it is neutral. Order: exposure and white
+// balance as channel gains, contrast, lift/gamma/gain, saturation, then the LUT.
+// Animated gains and contrast are a lutrgb updated frame by frame, `index` names its instance.
+fn build_color_filter(clip: &Clip, index: usize, settings: &ProjectSettings, time_offset: f64) -> Option<String> {
+    let color = clip.color_correction.clone().unwrap_or_default();
+    let kfs = clip.keyframes.as_ref();
+    let animated = |track: Option<&Vec<Keyframe>>| track.map(|k| !k.is_empty()).unwrap_or(false);
+
+    let exposure_kfs = kfs.and_then(|k| k.exposure.as_ref());
+    let contrast_kfs = kfs.and_then(|k| k.contrast.as_ref());
+    let saturation_kfs = kfs.and_then(|k| k.saturation.as_ref());
+    let temperature_kfs = kfs.and_then(|k| k.temperature.as_ref());
+    let tint_kfs = kfs.and_then(|k| k.tint.as_ref());
+
+    let mut filters = Vec::new();
+
+    // Channel gains (exposure, temperature, tint) and contrast around mid grey
+    let keyframed = [exposure_kfs, contrast_kfs, temperature_kfs, tint_kfs].iter().any(|k| animated(*k));
+    if keyframed {
+        // One lookup table per channel, rebuilt by sendcmd when a value changes. The curves
+        // travel inside sendcmd so they have no commas: lutrgb clamps the result itself
+        let target = format!("lutrgb@color{}", index);
+        let curves = |t: f64| {
+            let exposure = 2f64.powf(track_value_at(color.exposure, exposure_kfs, 0.0, t));
+            let contrast = track_value_at(color.contrast, contrast_kfs, 1.0, t);
+            let temperature = track_value_at(color.temperature, temperature_kfs, 0.0, t);
+            let tint = track_value_at(color.tint, tint_kfs, 0.0, t);
+            let curve = |gain: f64| format!("(val*{:.4}-maxval/2)*{:.4}+maxval/2", exposure * gain, contrast);
+            vec![
+                (target.clone(), "r", curve(1.0 + 0.25 * temperature)),
+                (target.clone(), "g", curve(1.0 - 0.25 * tint)),
+                (target.clone(), "b", curve(1.0 - 0.25 * temperature)),
+            ]
         };
+        let initial = curves(-time_offset);
+        if let Some(sendcmd) = build_keyframe_sendcmd(-time_offset, clip.duration, settings.fps as f64, curves) {
+            filters.push(sendcmd);
+        }
+        filters.push(format!("{}=r='{}':g='{}':b='{}'", target, initial[0].2, initial[1].2, initial[2].2));
+    } else {
+        let exposure = 2f64.powf(color.exposure.unwrap_or(0.0));
+        let temperature = color.temperature.unwrap_or(0.0);
+        let tint = color.tint.unwrap_or(0.0);
+        let gains = [
+            exposure * (1.0 + 0.25 * temperature),
+            exposure * (1.0 - 0.25 * tint),
+            exposure * (1.0 - 0.25 * temperature),
+        ];
+        if gains.iter().any(|g| (g - 1.0).abs() > 1e-6) {
+            filters.push(format!("colorchannelmixer=rr={:.4}:gg={:.4}:bb={:.4}", gains[0], gains[1], gains[2]));
+        }
+    }
 
-        // if(t < tempo_do_proximo, interpola, recursão_para_proximos)
-        format!("if(lt(t,{:.3}),{},{})", next.time, lerp, build_expression(keys, index + 1))
+    // Static contrast and the wheels in one lookup table: x -> (gain*x + lift*(1-x))^(1/gamma)
+    let contrast = if keyframed { 1.0 } else { color.contrast.unwrap_or(1.0) };
+    let lift = color.lift.unwrap_or(0.0);
+    let gamma = color.gamma.unwrap_or(1.0).max(0.01);
+    let gain = color.gain.unwrap_or(1.0);
+    if (contrast - 1.0).abs() > 1e-6 || lift.abs() > 1e-6 || (gamma - 1.0).abs() > 1e-6 || (gain - 1.0).abs() > 1e-6 {
+        let curve = format!(
+            "'clip(maxval*pow(clip({g:.4}*clip((val/maxval-0.5)*{c:.4}+0.5,0,1)+{l:.4}*(1-clip((val/maxval-0.5)*{c:.4}+0.5,0,1)),0,1),1/{gm:.4}),minval,maxval)'",
+            g = gain, c = contrast, l = lift, gm = gamma
+        );
+        filters.push(format!("lutrgb=r={c}:g={c}:b={c}", c = curve));
     }
 
-    // Se o primeiro keyframe não começar em 0s, definimos o valor inicial até lá
-    if sorted_keys[0].time > 0.0 {
-        format!("if(lt(t,{:.3}),{:.3},{})", 
-            sorted_keys[0].time, 
-            sorted_keys[0].value, 
-            build_expression(&sorted_keys, 0)
-        )
+    if animated(saturation_kfs) {
+        filters.push(format!("hue=s='max(0,{})'", transform_value(color.saturation, saturation_kfs, 1.0, "t")));
+    } else if let Some(saturation) = color.saturation.filter(|s| (s - 1.0).abs() > 1e-6) {
+        filters.push(format!("hue=s={:.4}", saturation.max(0.0)));
+    }
+
+    if let Some(lut) = color.lut.as_deref().filter(|l| !l.is_empty()) {
+        filters.push(format!("lut3d=file={}", escape_filter_value(lut)));
+    }
+
+    if filters.is_empty() {
+        None
     } else {
-        build_expression(&sorted_keys, 0)
+        Some(filters.join(","))
     }
 }
 
-
+// Cancels one job, or every pending job without `job_id`
 #[tauri::command]
-async fn cancel_export(state: State<'_, ExportState>) -> Result<(), String> {
-    let mut lock = state.0.lock().unwrap();
-    if let Some(child) = lock.take() {
+async fn cancel_export(app_handle: tauri::AppHandle, job_id: Option<String>) -> Result<(), String> {
+    let (cancelled, children) = {
+        let state = app_handle.state::<ExportState>();
+        let mut queue = state.0.lock().unwrap();
+        let mut cancelled = Vec::new();
+        for job in queue.jobs.iter_mut() {
+            let pending = matches!(job.status, ExportJobStatus::Queued | ExportJobStatus::Running);
+            if pending && job_id.as_ref().map(|id| *id == job.id).unwrap_or(true) {
+                job.status = ExportJobStatus::Cancelled;
+                cancelled.push(job.clone());
+            }
+        }
+        let children: Vec<CommandChild> = cancelled.iter().filter_map(|job| queue.running.remove(&job.id)).collect();
+        // Jobs that never started have nothing running to clean up after them
+        for job in &cancelled {
+            if queue.prepared.remove(&job.id).is_some() {
+                remove_export_graphs(job);
+            }
+        }
+        (cancelled, children)
+    };
+
+    // Um kill que falha não impede o resto: os jobs já estão cancelados e a fila anda
+    let mut errors = Vec::new();
+    for child in children {
         //  Kill the Tauri Sidecar process
-        child.kill().map_err(|e| format!("Failed to kill process: {}", e))?;
+        if let Err(e) = child.kill() {
+            eprintln!("Failed to kill process: {}", e);
+            errors.push(format!("Failed to kill process: {}", e));
+        }
     }
-    Ok(())
+    for job in &cancelled {
+        let _ = app_handle.emit("export-job", job.update());
+        save_export_queue(&app_handle, &job.project_path);
+    }
+    pump_export_queue(&app_handle);
+    if errors.is_empty() { Ok(()) } else { Err(errors.join("\n")) }
 }
 
 #[tauri::command]
@@ -703,14 +3844,24 @@ async fn generate_thumbnail(
     }
     // Paths based on project structure
     let video_path = PathBuf::from(&project_path).join("videos").join(&file_name);
+    let video_path = ready_proxy_for(&video_path).unwrap_or(video_path);
     let output_name = format!("{}-{}.png", file_name, time_seconds);
-    let output_path = PathBuf::from(&project_path).join("thumbnails").join(&output_name);
+    let output_path = thumbnail_folder.join(&output_name);
 
     // If the thumbnail already exists, skip generation to save resources
     if output_path.exists() {
         return Ok(output_path.to_string_lossy().into_owned());
     }
 
+    // Animations are decoded here, ffmpeg cannot read animated WebP
+    if let Some(frame) = animation_frame_at(&video_path, time_seconds, true) {
+        frame.save(&output_path).map_err(|e| e.to_string())?;
+        return Ok(output_path.to_string_lossy().into_owned());
+    }
+
+    let input_args = image_sequence_input_args(&video_path)
+        .unwrap_or_else(|| vec!["-i".to_string(), video_path.to_string_lossy().into_owned()]);
+
     // Execute FFmpeg Sidecar
     // -ss: fast seek to timestamp / -i: input / -frames:v 1: capture one frame / -q:v 2: quality level
     let sidecar_command = app_handle
@@ -719,7 +3870,9 @@ async fn generate_thumbnail(
         .map_err(|e| e.to_string())?
         .args([
             "-ss", &time_seconds.to_string(), // Seek to specific time
-            "-i", &video_path.to_string_lossy(), // Input source
+        ])
+        .args(&input_args) // Input source
+        .args([
             "-frames:v", "1", // Grab exactly 1 frame
             "-update", "1",   // ESSENTIAL: Specifies a single image output rather than a sequence
             "-y",             // Overwrite if exists (prevents hanging on prompts)
@@ -792,138 +3945,728 @@ async fn wait_for_process(state: State<'_, ExportState>) -> Result<(), String> {
                 return Err("Export cancelled".into());
             }
         }
-        // Wait a bit before checking again to save CPU
-        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        // Wait a bit before checking again to save CPU
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    }
+}
+
+*/
+
+#[tauri::command]
+fn list_project_files(project_path: String) -> Result<Vec<String>, String> {
+    let paths = fs::read_dir(project_path).map_err(|e| e.to_string())?;
+    let mut files: Vec<String> = paths
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".project"))
+        .collect();
+    files.sort(); // Sort by name (timestamp-based sorting)
+    Ok(files)
+}
+
+#[tauri::command]
+fn read_specific_file(project_path: String, file_name: String) -> Result<String, String> {
+    let mut path = PathBuf::from(project_path);
+    path.push(file_name);
+    fs::read_to_string(path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_project_data(project_path: String, data: String, timestamp: u64) -> Result<(), String> {
+    let mut path = PathBuf::from(&project_path);
+    let filename = format!("main{}.project", timestamp);
+    path.push(filename);
+
+    // 1. Write the new file
+    fs::write(&path, data).map_err(|e| e.to_string())?;
+
+    // 2. Clean up old files (Keep only the 50,000 newest)
+    let paths = fs::read_dir(&project_path).map_err(|e| e.to_string())?;
+    let mut project_files: Vec<_> = paths
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("project"))
+        .collect();
+
+    // Sort by name (which includes timestamp)
+    project_files.sort();
+
+    // If we exceed the limit, delete the oldest ones
+    let limit = 50000;
+    if project_files.len() > limit {
+        let to_delete = project_files.len() - limit;
+        for i in 0..to_delete {
+            let _ = fs::remove_file(&project_files[i]);
+        }
+    }
+
+    Ok(())
+}
+
+// Function to load the last saved state of the project
+#[tauri::command]
+fn load_latest_project(project_path: String) -> Result<String, String> {
+    let paths = fs::read_dir(project_path).map_err(|e| e.to_string())?;
+    
+    // Filter files ending with .project and find the one with the highest timestamp in name
+    let mut project_files: Vec<_> = paths
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("project"))
+        .collect();
+
+    project_files.sort(); // Sorts alphabetically/numerically
+    
+    if let Some(latest) = project_files.last() {
+        fs::read_to_string(latest).map_err(|e| e.to_string())
+    } else {
+        Err("No project file found".into())
+    }
+}
+
+#[tauri::command]
+fn load_specific_project(project_path: String, file_name: String) -> Result<String, String> {
+    // 1. Construct the full path: project_path/file_name
+    let mut path = PathBuf::from(&project_path);
+    path.push(&file_name);
+
+    // 2. Validate that the file exists and is indeed a file
+    if !path.exists() {
+        return Err(format!("File not found: {}", file_name));
+    }
+
+    // 3. Read content and return as String (JSON)
+    fs::read_to_string(path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn download_youtube_video(project_path: String, url: String) -> Result<String, String> {
+    let mut download_path = std::path::PathBuf::from(&project_path);
+    download_path.push("videos");
+
+    let output = Command::new("yt-dlp")
+        .args([
+            "--no-check-certificate",
+            "--prefer-free-formats",
+            "--add-header", "User-Agent:Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+            "-f", "bestvideo[ext=mp4]+bestaudio[ext=m4a]/best[ext=mp4]/best",
+            "--merge-output-format", "mp4",
+            "-o", &format!("{}/%(title)s.%(ext)s", download_path.to_string_lossy()),
+            &url,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+
+    if output.status.success() {
+        Ok("Download completed successfully".into())
+    } else {
+        let err = String::from_utf8_lossy(&output.stderr);
+        Err(format!("yt-dlp error: {}", err))
+    }
+}
+
+// Copies a file into videos/ of the project. Videos are checked for variable frame rate,
+// which makes trims drift against the audio; with `conform` such a video is transcoded to the
+// frame rate of the project and the original kept in originals/.
+#[tauri::command]
+async fn import_asset(
+    app_handle: tauri::AppHandle,
+    project_path: String,
+    file_path: String,
+    conform: Option<bool>,
+) -> Result<AssetRecord, String> {
+    let source = PathBuf::from(&file_path);
+    let filename = source.file_name().ok_or("Invalid file name")?;
+    
+    let mut target = PathBuf::from(&project_path);
+    target.push("videos");
+    target.push(filename);
+
+    fs::copy(&source, &target).map_err(|e| e.to_string())?;
+
+    let info = probe_media_with_cache(&app_handle, Some(Path::new(&project_path)), &target.to_string_lossy()).ok();
+    let frame_rate = info.as_ref().and_then(|info| info.video_stream()).and_then(|v| v.frame_rate);
+    let is_video = info.as_ref().map(|info| info.is_video()).unwrap_or(false);
+
+    // The file is already in the project: a failed check leaves it as constant frame rate
+    // with a warning instead of a copy without record
+    let (vfr_ratio, warning) = if is_video {
+        match detect_variable_frame_rate(&app_handle, &target).await {
+            Ok(ratio) => (ratio, None),
+            Err(e) => (0.0, Some(format!("Não foi possível verificar o frame rate: {}", e))),
+        }
+    } else {
+        (0.0, None)
+    };
+
+    let mut record = AssetRecord {
+        name: filename.to_string_lossy().into_owned(),
+        source: file_path.clone(),
+        original: target.to_string_lossy().into_owned(),
+        conformed: None,
+        variable_frame_rate: vfr_ratio > VFR_THRESHOLD,
+        vfr_ratio,
+        frame_rate,
+        warning,
+    };
+    if record.variable_frame_rate && conform.unwrap_or(false) {
+        conform_asset_file(&app_handle, Path::new(&project_path), &mut record).await?;
+    }
+    save_asset_record(&app_handle, Path::new(&project_path), &record)?;
+
+    Ok(record)
+}
+
+// Share of frames with a different duration than the previous one above which a video is
+// treated as variable frame rate; constant rate files with timestamp jitter stay below it
+const VFR_THRESHOLD: f64 = 0.01;
+
+// Only the start of a file is decoded to detect variable frame rate
+const VFR_SCAN_SECONDS: f64 = 120.0;
+
+// Runs the vfrdet filter over the start of a video and returns its VFR ratio
+async fn detect_variable_frame_rate(app_handle: &tauri::AppHandle, path: &Path) -> Result<f64, String> {
+    let output = app_handle
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| format!("Sidecar não encontrado: {}", e))?
+        .args([
+            "-hide_banner".to_string(),
+            "-t".to_string(), VFR_SCAN_SECONDS.to_string(),
+            "-i".to_string(), path.to_string_lossy().into_owned(),
+            "-map".to_string(), "0:v:0".to_string(),
+            "-vf".to_string(), "vfrdet".to_string(),
+            "-f".to_string(), "null".to_string(),
+            "-".to_string(),
+        ])
+        .output()
+        .await
+        .map_err(|e| format!("Falha ao iniciar o FFmpeg: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Falha ao analisar o frame rate: {}", String::from_utf8_lossy(&output.stderr).lines().last().unwrap_or_default()));
+    }
+    Ok(parse_vfrdet_output(&String::from_utf8_lossy(&output.stderr)).unwrap_or(0.0))
+}
+
+// vfrdet prints "VFR:0.123456 (12/988) min: 1 max: 2 avg: 1" when the input ends
+fn parse_vfrdet_output(stderr: &str) -> Option<f64> {
+    let line = stderr.lines().rev().find(|line| line.contains("VFR:"))?;
+    let value = line.split("VFR:").nth(1)?.split_whitespace().next()?;
+    value.parse::<f64>().ok()
+}
+
+fn build_conform_args(original: &Path, output: &Path, fps: f64) -> Vec<String> {
+    let webm = output.extension().map(|e| e.eq_ignore_ascii_case("webm")).unwrap_or(false);
+    let (video_codec, audio_codec) = if webm { ("libvpx-vp9", "libopus") } else { ("libx264", "aac") };
+    let mut args: Vec<String> = vec![
+        "-y".into(),
+        "-hide_banner".into(),
+        "-i".into(), original.to_string_lossy().into_owned(),
+        "-map".into(), "0:v:0".into(),
+        "-map".into(), "0:a?".into(),
+        // Constant frame rate at the project rate, audio stretched/padded to the same clock
+        "-vf".into(), format!("fps={}", fps),
+        "-fps_mode".into(), "cfr".into(),
+        "-af".into(), "aresample=async=1:first_pts=0".into(),
+        "-c:v".into(), video_codec.into(),
+    ];
+    if webm {
+        args.extend(["-crf".into(), "20".into(), "-b:v".into(), "0".into()]);
+    } else {
+        args.extend(["-crf".into(), "16".into(), "-preset".into(), "medium".into(), "-pix_fmt".into(), "yuv420p".into()]);
+    }
+    args.extend(["-c:a".into(), audio_codec.into(), "-b:a".into(), "192k".into(), output.to_string_lossy().into_owned()]);
+    args
+}
+
+// Moves the imported video to originals/ and writes the constant frame rate version in its
+// place in videos/, so every clip, proxy and thumbnail of it uses the conformed file
+async fn conform_asset_file(app_handle: &tauri::AppHandle, project_dir: &Path, record: &mut AssetRecord) -> Result<(), String> {
+    let fps = read_project_settings(project_dir).map(|s| s.fps).unwrap_or_else(|_| ProjectSettings::default().fps);
+    let originals_dir = project_dir.join("originals");
+    fs::create_dir_all(&originals_dir).map_err(|e| format!("Erro ao criar originals: {}", e))?;
+
+    let video_path = project_dir.join("videos").join(&record.name);
+    let original_path = originals_dir.join(&record.name);
+    fs::rename(&video_path, &original_path).map_err(|e| format!("Erro ao mover o original: {}", e))?;
+
+    let output = app_handle
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| format!("Sidecar não encontrado: {}", e))?
+        .args(build_conform_args(&original_path, &video_path, fps))
+        .output()
+        .await
+        .map_err(|e| format!("Falha ao iniciar o FFmpeg: {}", e))?;
+    if !output.status.success() {
+        // Put the original back so the asset stays usable
+        let _ = fs::remove_file(&video_path);
+        let _ = fs::rename(&original_path, &video_path);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let lines: Vec<&str> = stderr.lines().collect();
+        return Err(format!("Erro ao converter para frame rate constante: {}", lines[lines.len().saturating_sub(10)..].join("\n")));
+    }
+
+    record.original = original_path.to_string_lossy().into_owned();
+    record.conformed = Some(video_path.to_string_lossy().into_owned());
+    record.frame_rate = Some(fps);
+    Ok(())
+}
+
+// Reads assets.json; callers hold AssetsState
+fn read_asset_records(project_dir: &Path) -> HashMap<String, AssetRecord> {
+    fs::read_to_string(project_dir.join("assets.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_asset_record(app_handle: &tauri::AppHandle, project_dir: &Path, record: &AssetRecord) -> Result<(), String> {
+    let state = app_handle.state::<AssetsState>();
+    let _guard = state.0.lock().unwrap();
+    let mut records = read_asset_records(project_dir);
+    records.insert(record.name.clone(), record.clone());
+    let json = serde_json::to_string_pretty(&records).map_err(|e| e.to_string())?;
+    fs::write(project_dir.join("assets.json"), json).map_err(|e| format!("Erro ao gravar assets.json: {}", e))
+}
+
+// Extension of the image sequence descriptors in videos/
+const IMAGE_SEQUENCE_EXTENSION: &str = ".imgseq";
+
+// Frame formats recognised as sequences
+const IMAGE_SEQUENCE_FORMATS: [&str; 6] = ["png", "jpg", "jpeg", "exr", "tif", "tiff"];
+
+// Splits "shot_0012.exr" into ("shot_", "0012", "exr")
+fn split_frame_name(name: &str) -> Option<(&str, &str, &str)> {
+    let (stem, extension) = name.rsplit_once('.')?;
+    if !IMAGE_SEQUENCE_FORMATS.contains(&extension.to_lowercase().as_str()) {
+        return None;
+    }
+    let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    let digits = &stem[prefix.len()..];
+    if digits.is_empty() {
+        return None;
+    }
+    Some((prefix, digits, extension))
+}
+
+// Numbered sequences among the files of a folder, biggest first. Frames are taken from the
+// first number up to the first missing one, which is where ffmpeg stops reading.
+fn find_image_sequences(dir: &Path, frame_rate: f64) -> Vec<ImageSequence> {
+    let mut groups: HashMap<(String, String), Vec<(u64, String)>> = HashMap::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten().filter(|e| e.path().is_file()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some((prefix, digits, extension)) = split_frame_name(&name) {
+                if let Ok(number) = digits.parse::<u64>() {
+                    groups
+                        .entry((prefix.to_string(), extension.to_string()))
+                        .or_default()
+                        .push((number, digits.to_string()));
+                }
+            }
+        }
+    }
+
+    let mut sequences = Vec::new();
+    for ((prefix, extension), mut frames) in groups {
+        frames.sort();
+        // Padded numbers all have the same width; unpadded ones never start with 0
+        // A % in the name would be read as part of the pattern by ffmpeg
+        if prefix.contains('%') {
+            continue;
+        }
+        let width = frames[0].1.len();
+        let pattern_number = if frames.iter().all(|(_, digits)| digits.len() == width) {
+            format!("%0{}d", width)
+        } else if frames.iter().all(|(_, digits)| !digits.starts_with('0') || digits.len() == 1) {
+            "%d".to_string()
+        } else {
+            continue;
+        };
+        let start_number = frames[0].0;
+        let frame_count = frames.iter().enumerate().take_while(|(k, (n, _))| *n == start_number + *k as u64).count() as u64;
+        if frame_count < 2 {
+            continue;
+        }
+
+        let base = prefix.trim_end_matches(['_', '-', '.', ' ']);
+        let name = if base.is_empty() {
+            dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| "sequence".to_string())
+        } else {
+            base.to_string()
+        };
+        sequences.push(ImageSequence {
+            name,
+            directory: dir.to_string_lossy().into_owned(),
+            pattern: format!("{}{}.{}", prefix, pattern_number, extension),
+            start_number,
+            frame_count,
+            frame_rate,
+            width: None,
+            height: None,
+        });
+    }
+    sequences.sort_by(|a, b| b.frame_count.cmp(&a.frame_count).then_with(|| a.pattern.cmp(&b.pattern)));
+    sequences
+}
+
+// Name of frame `number`, filling the %0Nd (or %d) of the pattern
+fn image_sequence_frame_name(sequence: &ImageSequence, number: u64) -> String {
+    let Some((head, spec)) = sequence.pattern.split_once('%') else {
+        return sequence.pattern.clone();
+    };
+    let (width, tail) = spec.split_once('d').unwrap_or(("", spec));
+    let width = width.trim_start_matches('0').parse::<usize>().unwrap_or(0);
+    format!("{}{:0width$}{}", head, number, tail, width = width)
+}
+
+// The descriptor, when `path` is a videos/<name>.imgseq file
+fn read_image_sequence(path: &Path) -> Option<ImageSequence> {
+    if !path.to_string_lossy().to_lowercase().ends_with(IMAGE_SEQUENCE_EXTENSION) {
+        return None;
+    }
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+// Folder of the frames; `directory` is relative to the project holding the descriptor
+fn image_sequence_dir(descriptor: &Path, sequence: &ImageSequence) -> PathBuf {
+    match descriptor.parent().and_then(|videos| videos.parent()) {
+        Some(project_dir) => project_dir.join(&sequence.directory),
+        None => PathBuf::from(&sequence.directory),
+    }
+}
+
+// Input options reading a descriptor as a video stream at the frame rate of the sequence
+fn image_sequence_input_args(descriptor: &Path) -> Option<Vec<String>> {
+    let sequence = read_image_sequence(descriptor)?;
+    let mut args: Vec<String> = vec![
+        "-f".into(), "image2".into(),
+        "-framerate".into(), sequence.frame_rate.to_string(),
+        "-start_number".into(), sequence.start_number.to_string(),
+    ];
+    // EXR frames are linear light
+    if sequence.pattern.to_lowercase().ends_with(".exr") {
+        args.extend(["-apply_trc".into(), "iec61966_2_1".into()]);
+    }
+    args.extend(["-i".into(), image_sequence_dir(descriptor, &sequence).join(&sequence.pattern).to_string_lossy().into_owned()]);
+    Some(args)
+}
+
+// Frame file shown at `time` seconds into the sequence
+fn image_sequence_frame_at(descriptor: &Path, time: f64) -> Option<PathBuf> {
+    let sequence = read_image_sequence(descriptor)?;
+    let index = ((time.max(0.0) * sequence.frame_rate).floor() as u64).min(sequence.frame_count.saturating_sub(1));
+    Some(image_sequence_dir(descriptor, &sequence).join(image_sequence_frame_name(&sequence, sequence.start_number + index)))
+}
+
+fn image_sequence_media_info(path: &str, sequence: &ImageSequence, size: u64, modified: u64) -> MediaInfo {
+    let duration = sequence.frame_count as f64 / sequence.frame_rate;
+    MediaInfo {
+        path: path.to_string(),
+        format_name: "image2".to_string(),
+        duration,
+        bit_rate: None,
+        size,
+        modified,
+        streams: vec![StreamInfo {
+            index: 0,
+            kind: "video".to_string(),
+            codec: sequence.pattern.rsplit('.').next().unwrap_or_default().to_lowercase(),
+            profile: None,
+            duration: Some(duration),
+            bit_rate: None,
+            language: None,
+            width: sequence.width,
+            height: sequence.height,
+            frame_rate: Some(sequence.frame_rate),
+            real_frame_rate: Some(sequence.frame_rate),
+            frame_count: Some(sequence.frame_count),
+            pixel_format: None,
+            rotation: None,
+            channels: None,
+            channel_layout: None,
+            sample_rate: None,
+            bit_depth: None,
+        }],
+    }
+}
+
+// Frames of GIFs without a delay are shown for this long, as browsers do
+const ANIMATION_DEFAULT_DELAY: f64 = 0.1;
+
+#[derive(Clone)]
+pub struct AnimationInfo {
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+    pub delays: Vec<f64>,
+    pub has_alpha: bool,
+}
+
+impl AnimationInfo {
+    fn duration(&self) -> f64 {
+        self.delays.iter().sum()
+    }
+}
+
+// Frame decoder of an animated GIF or WebP; still WebP are not animations
+fn animation_frames(path: &Path) -> Option<image::Frames<'static>> {
+    use image::AnimationDecoder;
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let reader = std::io::BufReader::new(File::open(path).ok()?);
+    match extension.as_str() {
+        "gif" => Some(image::codecs::gif::GifDecoder::new(reader).ok()?.into_frames()),
+        "webp" => {
+            let decoder = image::codecs::webp::WebPDecoder::new(reader).ok()?;
+            if !decoder.has_animation() {
+                return None;
+            }
+            Some(decoder.into_frames())
+        }
+        _ => None,
     }
 }
 
-*/
-
-#[tauri::command]
-fn list_project_files(project_path: String) -> Result<Vec<String>, String> {
-    let paths = fs::read_dir(project_path).map_err(|e| e.to_string())?;
-    let mut files: Vec<String> = paths
-        .filter_map(|entry| entry.ok())
-        .map(|entry| entry.file_name().to_string_lossy().into_owned())
-        .filter(|name| name.ends_with(".project"))
-        .collect();
-    files.sort(); // Sort by name (timestamp-based sorting)
-    Ok(files)
+fn frame_delay(frame: &image::Frame) -> f64 {
+    let (numer, denom) = frame.delay().numer_denom_ms();
+    let delay = numer as f64 / denom.max(1) as f64 / 1000.0;
+    if delay < 0.01 { ANIMATION_DEFAULT_DELAY } else { delay }
 }
 
-#[tauri::command]
-fn read_specific_file(project_path: String, file_name: String) -> Result<String, String> {
-    let mut path = PathBuf::from(project_path);
-    path.push(file_name);
-    fs::read_to_string(path).map_err(|e| e.to_string())
+// animation_info of the files already decoded, with the modification time it is valid for.
+// Previews and thumbnails ask for it on every frame, decoding it each time is too slow.
+type AnimationInfoCache = Mutex<HashMap<PathBuf, (std::time::SystemTime, Option<AnimationInfo>)>>;
+
+fn animation_info_cache() -> &'static AnimationInfoCache {
+    static CACHE: std::sync::OnceLock<AnimationInfoCache> = std::sync::OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
-#[tauri::command]
-fn save_project_data(project_path: String, data: String, timestamp: u64) -> Result<(), String> {
-    let mut path = PathBuf::from(&project_path);
-    let filename = format!("main{}.project", timestamp);
-    path.push(filename);
+// Size, frame timing and transparency of an animation. Every frame is decoded the first
+// time, later calls come from animation_info_cache until the file changes.
+fn animation_info(path: &Path) -> Option<AnimationInfo> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    if extension != "gif" && extension != "webp" {
+        return None;
+    }
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    if let Some((cached_at, info)) = animation_info_cache().lock().unwrap().get(path) {
+        if *cached_at == modified {
+            return info.clone();
+        }
+    }
 
-    // 1. Write the new file
-    fs::write(&path, data).map_err(|e| e.to_string())?;
+    let info = decode_animation_info(path);
+    animation_info_cache().lock().unwrap().insert(path.to_path_buf(), (modified, info.clone()));
+    info
+}
 
-    // 2. Clean up old files (Keep only the 50,000 newest)
-    let paths = fs::read_dir(&project_path).map_err(|e| e.to_string())?;
-    let mut project_files: Vec<_> = paths
-        .filter_map(|entry| entry.ok())
-        .map(|entry| entry.path())
-        .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("project"))
-        .collect();
+fn decode_animation_info(path: &Path) -> Option<AnimationInfo> {
+    let mut info = AnimationInfo {
+        format: path.extension()?.to_string_lossy().to_lowercase(),
+        width: 0,
+        height: 0,
+        delays: Vec::new(),
+        has_alpha: false,
+    };
+    for frame in animation_frames(path)? {
+        let frame = frame.ok()?;
+        if info.delays.is_empty() {
+            (info.width, info.height) = frame.buffer().dimensions();
+        }
+        info.has_alpha |= frame.buffer().pixels().any(|p| p.0[3] < 255);
+        info.delays.push(frame_delay(&frame));
+    }
+    (!info.delays.is_empty()).then_some(info)
+}
 
-    // Sort by name (which includes timestamp)
-    project_files.sort();
+fn animation_media_info(path: &str, animation: &AnimationInfo, size: u64, modified: u64) -> MediaInfo {
+    let duration = animation.duration();
+    MediaInfo {
+        path: path.to_string(),
+        format_name: animation.format.clone(),
+        duration,
+        bit_rate: None,
+        size,
+        modified,
+        streams: vec![StreamInfo {
+            index: 0,
+            kind: "video".to_string(),
+            codec: animation.format.clone(),
+            profile: None,
+            duration: Some(duration),
+            bit_rate: None,
+            language: None,
+            width: Some(animation.width),
+            height: Some(animation.height),
+            frame_rate: Some(animation.delays.len() as f64 / duration),
+            real_frame_rate: None,
+            frame_count: Some(animation.delays.len() as u64),
+            pixel_format: Some(if animation.has_alpha { "rgba" } else { "rgb24" }.to_string()),
+            rotation: None,
+            channels: None,
+            channel_layout: None,
+            sample_rate: None,
+            bit_depth: Some(8),
+        }],
+    }
+}
 
-    // If we exceed the limit, delete the oldest ones
-    let limit = 50000;
-    if project_files.len() > limit {
-        let to_delete = project_files.len() - limit;
-        for i in 0..to_delete {
-            let _ = fs::remove_file(&project_files[i]);
+// Frame shown `time` seconds into an animation, repeating it when `looping`. The cached
+// delays give the frame index, only the frames up to it are decoded.
+fn animation_frame_at(path: &Path, time: f64, looping: bool) -> Option<image::RgbaImage> {
+    let info = animation_info(path)?;
+    let duration = info.duration();
+    let time = if looping && duration > 0.0 { time.max(0.0) % duration } else { time.max(0.0) };
+    let mut elapsed = 0.0;
+    let index = info
+        .delays
+        .iter()
+        .position(|delay| {
+            elapsed += delay;
+            elapsed > time
+        })
+        .unwrap_or(info.delays.len() - 1);
+    // GIF/WebP frames are deltas over the previous ones, so the decoder still goes through them
+    animation_frames(path)?.nth(index)?.ok().map(|frame| frame.into_buffer())
+}
+
+// Writes the frames of an animated WebP to animations/<name>/ of the project with an
+// .ffconcat list holding their durations, reused while newer than the file. None when the
+// file is a still.
+fn extract_animation_frames(project_dir: &Path, path: &Path) -> Result<Option<PathBuf>, String> {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let frames_dir = project_dir.join("animations").join(&name);
+    let list_path = frames_dir.join("animation.ffconcat");
+
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    if let (Some(list), Some(source)) = (modified(&list_path), modified(path)) {
+        if list >= source {
+            return Ok(Some(list_path));
         }
     }
+    let Some(frames) = animation_frames(path) else {
+        return Ok(None);
+    };
+
+    fs::create_dir_all(&frames_dir).map_err(|e| format!("Erro ao criar {}: {}", frames_dir.display(), e))?;
+    let mut list = String::from("ffconcat version 1.0\n");
+    let mut last = String::new();
+    for (index, frame) in frames.enumerate() {
+        let frame = frame.map_err(|e| format!("Erro ao decodificar {}: {}", name, e))?;
+        let frame_name = format!("frame_{:05}.png", index);
+        let delay = frame_delay(&frame);
+        frame.buffer().save(frames_dir.join(&frame_name)).map_err(|e| format!("Erro ao gravar {}: {}", frame_name, e))?;
+        list.push_str(&format!("file '{}'\nduration {:.4}\n", frame_name, delay));
+        last = frame_name;
+    }
+    // The concat demuxer ignores the duration of the last entry unless it is repeated
+    list.push_str(&format!("file '{}'\n", last));
+    fs::write(&list_path, list).map_err(|e| format!("Erro ao gravar {}: {}", list_path.display(), e))?;
 
-    Ok(())
+    Ok(Some(list_path))
 }
 
-// Function to load the last saved state of the project
+// Sequences found in a folder, or the one a frame file belongs to
 #[tauri::command]
-fn load_latest_project(project_path: String) -> Result<String, String> {
-    let paths = fs::read_dir(project_path).map_err(|e| e.to_string())?;
-    
-    // Filter files ending with .project and find the one with the highest timestamp in name
-    let mut project_files: Vec<_> = paths
-        .filter_map(|entry| entry.ok())
-        .map(|entry| entry.path())
-        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("project"))
-        .collect();
-
-    project_files.sort(); // Sorts alphabetically/numerically
-    
-    if let Some(latest) = project_files.last() {
-        fs::read_to_string(latest).map_err(|e| e.to_string())
-    } else {
-        Err("No project file found".into())
+fn detect_image_sequences(path: String, frame_rate: Option<f64>) -> Result<Vec<ImageSequence>, String> {
+    let path = Path::new(&path);
+    let frame_rate = frame_rate.unwrap_or_else(|| ProjectSettings::default().fps);
+    if path.is_dir() {
+        return Ok(find_image_sequences(path, frame_rate));
     }
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let (prefix, _, extension) = split_frame_name(&name).ok_or("O arquivo não faz parte de uma sequência numerada")?;
+    let dir = path.parent().ok_or("Pasta inválida")?;
+    // Same prefix, not just the same start: shot_%04d.png is not shot_a_%04d.png
+    let suffix = format!("d.{}", extension);
+    Ok(find_image_sequences(dir, frame_rate)
+        .into_iter()
+        .filter(|s| match s.pattern.rsplit_once('%') {
+            Some((pattern_prefix, spec)) => pattern_prefix == prefix && spec.ends_with(&suffix),
+            None => false,
+        })
+        .collect())
 }
 
+// Imports the sequence found at `path` (folder or one of its frames) as a single asset.
+// Without `frame_rate` the sequence runs at the frame rate of the project.
 #[tauri::command]
-fn load_specific_project(project_path: String, file_name: String) -> Result<String, String> {
-    // 1. Construct the full path: project_path/file_name
-    let mut path = PathBuf::from(&project_path);
-    path.push(&file_name);
+async fn import_image_sequence(project_path: String, path: String, frame_rate: Option<f64>) -> Result<String, String> {
+    let project_dir = Path::new(&project_path);
+    let frame_rate = frame_rate
+        .or_else(|| read_project_settings(project_dir).ok().map(|s| s.fps))
+        .filter(|fps| *fps > 0.0)
+        .unwrap_or_else(|| ProjectSettings::default().fps);
+    let mut sequence = detect_image_sequences(path.clone(), Some(frame_rate))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("Nenhuma sequência de imagens em {}", path))?;
+
+    // Name not taken by another asset or sequence of the project
+    let base_name = sequence.name.clone();
+    let mut suffix = 1;
+    while project_dir.join("videos").join(format!("{}{}", sequence.name, IMAGE_SEQUENCE_EXTENSION)).exists()
+        || project_dir.join("sequences").join(&sequence.name).exists()
+    {
+        suffix += 1;
+        sequence.name = format!("{}_{}", base_name, suffix);
+    }
 
-    // 2. Validate that the file exists and is indeed a file
-    if !path.exists() {
-        return Err(format!("File not found: {}", file_name));
+    let source_dir = PathBuf::from(&sequence.directory);
+    let relative_dir = Path::new("sequences").join(&sequence.name);
+    let target_dir = project_dir.join(&relative_dir);
+    fs::create_dir_all(&target_dir).map_err(|e| format!("Erro ao criar {}: {}", target_dir.display(), e))?;
+    for number in sequence.start_number..sequence.start_number + sequence.frame_count {
+        let frame = image_sequence_frame_name(&sequence, number);
+        fs::copy(source_dir.join(&frame), target_dir.join(&frame)).map_err(|e| format!("Erro ao copiar {}: {}", frame, e))?;
     }
 
-    // 3. Read content and return as String (JSON)
-    fs::read_to_string(path).map_err(|e| e.to_string())
-}
+    let first_frame = target_dir.join(image_sequence_frame_name(&sequence, sequence.start_number));
+    if let Ok(info) = probe_media_file(&first_frame.to_string_lossy()) {
+        if let Some(video) = info.video_stream() {
+            sequence.width = video.width;
+            sequence.height = video.height;
+        }
+    }
+    sequence.directory = relative_dir.to_string_lossy().into_owned();
 
-#[tauri::command]
-async fn download_youtube_video(project_path: String, url: String) -> Result<String, String> {
-    let mut download_path = std::path::PathBuf::from(&project_path);
-    download_path.push("videos");
+    let descriptor_name = format!("{}{}", sequence.name, IMAGE_SEQUENCE_EXTENSION);
+    let json = serde_json::to_string_pretty(&sequence).map_err(|e| e.to_string())?;
+    fs::write(project_dir.join("videos").join(&descriptor_name), json).map_err(|e| format!("Erro ao gravar {}: {}", descriptor_name, e))?;
 
-    let output = Command::new("yt-dlp")
-        .args([
-            "--no-check-certificate",
-            "--prefer-free-formats",
-            "--add-header", "User-Agent:Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
-            "-f", "bestvideo[ext=mp4]+bestaudio[ext=m4a]/best[ext=mp4]/best",
-            "--merge-output-format", "mp4",
-            "-o", &format!("{}/%(title)s.%(ext)s", download_path.to_string_lossy()),
-            &url,
-        ])
-        .output()
-        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+    Ok(descriptor_name)
+}
 
-    if output.status.success() {
-        Ok("Download completed successfully".into())
-    } else {
-        let err = String::from_utf8_lossy(&output.stderr);
-        Err(format!("yt-dlp error: {}", err))
+// Conforms an asset imported earlier, e.g. after the user accepted the VFR warning
+#[tauri::command]
+async fn conform_asset(app_handle: tauri::AppHandle, project_path: String, file_name: String) -> Result<AssetRecord, String> {
+    let project_dir = Path::new(&project_path);
+    let mut records = {
+        let state = app_handle.state::<AssetsState>();
+        let _guard = state.0.lock().unwrap();
+        read_asset_records(project_dir)
+    };
+    let mut record = records
+        .remove(&file_name)
+        .ok_or_else(|| format!("Asset sem registro de importação: {}", file_name))?;
+    if record.conformed.is_none() {
+        conform_asset_file(&app_handle, project_dir, &mut record).await?;
+        save_asset_record(&app_handle, project_dir, &record)?;
     }
+    Ok(record)
 }
 
 #[tauri::command]
-async fn import_asset(project_path: String, file_path: String) -> Result<String, String> {
-    let source = PathBuf::from(&file_path);
-    let filename = source.file_name().ok_or("Invalid file name")?;
-    
-    let mut target = PathBuf::from(&project_path);
-    target.push("videos");
-    target.push(filename);
-
-    fs::copy(&source, &target).map_err(|e| e.to_string())?;
-    
-    Ok(filename.to_string_lossy().into_owned())
+fn list_asset_records(state: State<'_, AssetsState>, project_path: String) -> Vec<AssetRecord> {
+    let mut records: Vec<AssetRecord> = {
+        let _guard = state.0.lock().unwrap();
+        read_asset_records(Path::new(&project_path)).into_values().collect()
+    };
+    records.sort_by(|a, b| a.name.cmp(&b.name));
+    records
 }
 
 #[tauri::command]
@@ -1013,23 +4756,181 @@ fn delete_file(path: String) -> Result<(), String> {
     Ok(())
 }
 
+// Duration of a media file, from probe_media
 #[command]
-async fn get_duration(path: String) -> Result<VideoMetadata, String> {
-    // Command: ffprobe -v error -show_entries format=duration -of default=noprint_wrappers=1:nokey=1 path
-    let output = Command::new("ffprobe")
-        .args([
-            "-v", "error",
-            "-show_entries", "format=duration",
-            "-of", "default=noprint_wrappers=1:nokey=1",
-            &path,
-        ])
+async fn get_duration(app_handle: tauri::AppHandle, path: String, project_path: Option<String>) -> Result<VideoMetadata, String> {
+    let info = probe_media_with_cache(&app_handle, project_path.as_deref().map(Path::new), &path)?;
+    Ok(VideoMetadata { duration: info.duration })
+}
+
+// Full probe of a file. With `project_path` the result is cached in media_info.json of the
+// project and only probed again when the file changes.
+#[tauri::command]
+async fn probe_media(app_handle: tauri::AppHandle, path: String, project_path: Option<String>) -> Result<MediaInfo, String> {
+    probe_media_with_cache(&app_handle, project_path.as_deref().map(Path::new), &path)
+}
+
+// ffprobe numbers come as strings ("48000", "30000/1001")
+fn probe_number(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(text) => match text.split_once('/') {
+            Some((num, den)) => {
+                let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+                if den == 0.0 || num == 0.0 { None } else { Some(num / den) }
+            }
+            None => text.parse::<f64>().ok(),
+        },
+        _ => None,
+    }
+}
+
+fn parse_stream_info(stream: &serde_json::Value) -> StreamInfo {
+    let number = |key: &str| probe_number(&stream[key]);
+    let text = |key: &str| stream[key].as_str().filter(|v| !v.is_empty() && *v != "unknown").map(|v| v.to_string());
+
+    // Rotation from the display matrix, or the old `rotate` tag
+    let rotation = stream["side_data_list"]
+        .as_array()
+        .and_then(|list| list.iter().find_map(|side| probe_number(&side["rotation"])))
+        .or_else(|| probe_number(&stream["tags"]["rotate"]).map(|r| -r));
+
+    StreamInfo {
+        index: number("index").unwrap_or(0.0) as u32,
+        kind: text("codec_type").unwrap_or_else(|| "data".to_string()),
+        codec: text("codec_name").unwrap_or_default(),
+        profile: text("profile"),
+        duration: number("duration"),
+        bit_rate: number("bit_rate").map(|v| v as u64),
+        language: stream["tags"]["language"].as_str().map(|v| v.to_string()),
+        width: number("width").map(|v| v as u32),
+        height: number("height").map(|v| v as u32),
+        frame_rate: number("avg_frame_rate"),
+        real_frame_rate: number("r_frame_rate"),
+        frame_count: number("nb_frames").map(|v| v as u64),
+        pixel_format: text("pix_fmt"),
+        rotation,
+        channels: number("channels").map(|v| v as u32),
+        channel_layout: text("channel_layout"),
+        sample_rate: number("sample_rate").map(|v| v as u32),
+        bit_depth: number("bits_per_raw_sample")
+            .or_else(|| number("bits_per_sample"))
+            .filter(|v| *v > 0.0)
+            .map(|v| v as u32),
+    }
+}
+
+// Size and modification time (seconds) of a file, compared with the cached probe
+fn file_signature(path: &str) -> Result<(u64, u64), String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("Arquivo não encontrado: {} ({})", path, e))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok((metadata.len(), modified))
+}
+
+// ffprobe is not bundled like the ffmpeg sidecar: the one next to the executable is used
+// when there is one, else the one in the PATH
+fn ffprobe_command() -> Command {
+    let name = if cfg!(windows) { "ffprobe.exe" } else { "ffprobe" };
+    let bundled = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(name)))
+        .filter(|path| path.is_file());
+    match bundled {
+        Some(path) => Command::new(path),
+        None => Command::new(name),
+    }
+}
+
+// Runs ffprobe on a file
+fn probe_media_file(path: &str) -> Result<MediaInfo, String> {
+    let (size, modified) = file_signature(path)?;
+    if let Some(sequence) = read_image_sequence(Path::new(path)) {
+        return Ok(image_sequence_media_info(path, &sequence, size, modified));
+    }
+    if let Some(animation) = animation_info(Path::new(path)) {
+        return Ok(animation_media_info(path, &animation, size, modified));
+    }
+    let output = ffprobe_command()
+        .args(["-v", "error", "-show_format", "-show_streams", "-of", "json", path])
         .output()
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                "ffprobe não encontrado: instale o FFmpeg (com o ffprobe) no PATH ou coloque o ffprobe ao lado do executável".to_string()
+            }
+            _ => format!("Falha ao executar o ffprobe: {}", e),
+        })?;
+    if !output.status.success() {
+        return Err(format!("ffprobe falhou: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    let probe: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Saída do ffprobe inválida: {}", e))?;
+    let streams: Vec<StreamInfo> = probe["streams"]
+        .as_array()
+        .map(|streams| streams.iter().map(parse_stream_info).collect())
+        .unwrap_or_default();
+
+    // Stills have no duration
+    let duration = probe_number(&probe["format"]["duration"])
+        .or_else(|| streams.iter().filter_map(|s| s.duration).reduce(f64::max))
+        .unwrap_or(0.0);
+
+    Ok(MediaInfo {
+        path: path.to_string(),
+        format_name: probe["format"]["format_name"].as_str().unwrap_or_default().to_string(),
+        duration,
+        bit_rate: probe_number(&probe["format"]["bit_rate"]).map(|v| v as u64),
+        size,
+        modified,
+        streams,
+    })
+}
+
+fn read_media_info_cache(cache_path: &Path) -> HashMap<String, MediaInfo> {
+    fs::read_to_string(cache_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
 
-    let duration_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    let duration = duration_str.parse::<f64>().map_err(|_| "Failed to parse duration")?;
+// Probe through the media_info.json cache of the project, when there is one. The file is
+// only touched with MediaInfoState locked; ffprobe itself runs without the lock.
+fn probe_media_with_cache(app_handle: &tauri::AppHandle, project_dir: Option<&Path>, path: &str) -> Result<MediaInfo, String> {
+    let project_dir = match project_dir {
+        Some(dir) if dir.is_dir() => dir,
+        _ => return probe_media_file(path),
+    };
+    let state = app_handle.state::<MediaInfoState>();
+    let cache_path = project_dir.join("media_info.json");
+
+    let (size, modified) = file_signature(path)?;
+    {
+        let _guard = state.0.lock().unwrap();
+        let cache = read_media_info_cache(&cache_path);
+        if let Some(info) = cache.get(path).filter(|info| info.size == size && info.modified == modified) {
+            return Ok(info.clone());
+        }
+    }
 
-    Ok(VideoMetadata { duration })
+    let info = probe_media_file(path)?;
+
+    // Read again, other probes may have written it meanwhile
+    let _guard = state.0.lock().unwrap();
+    let mut cache = read_media_info_cache(&cache_path);
+    cache.insert(path.to_string(), info.clone());
+    // The probe itself worked, a cache that cannot be written only costs the next probe
+    let written = serde_json::to_string_pretty(&cache)
+        .map_err(|e| e.to_string())
+        .and_then(|json| fs::write(&cache_path, json).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        eprintln!("Erro ao gravar media_info.json: {}", e);
+    }
+    Ok(info)
 }
 
 
@@ -1040,6 +4941,27 @@ use base64::{engine::general_purpose, Engine as _};
 
 #[tauri::command]
 async fn get_video_frame(path: String, time_ms: f64) -> Result<String, String> {
+    let path = ready_proxy_for(Path::new(&path))
+        .map(|proxy| proxy.to_string_lossy().into_owned())
+        .unwrap_or(path);
+
+    // Animations keep their transparency, hence PNG
+    if let Some(frame) = animation_frame_at(Path::new(&path), time_ms / 1000.0, true) {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgba8(frame)
+            .write_to(&mut buffer, image::ImageOutputFormat::Png)
+            .map_err(|e| e.to_string())?;
+        return Ok(format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(buffer.into_inner())));
+    }
+
+    // Sequences are read straight from the frame file at that time
+    if let Some(frame_path) = image_sequence_frame_at(Path::new(&path), time_ms / 1000.0) {
+        let frame = imgcodecs::imread(&frame_path.to_string_lossy(), imgcodecs::IMREAD_COLOR).map_err(|e| e.to_string())?;
+        let mut buffer = core::Vector::<u8>::new();
+        imgcodecs::imencode(".jpg", &frame, &mut buffer, &core::Vector::default()).map_err(|e| e.to_string())?;
+        return Ok(format!("data:image/jpeg;base64,{}", general_purpose::STANDARD.encode(buffer.as_slice())));
+    }
+
     let mut cam = videoio::VideoCapture::from_file(&path, videoio::CAP_ANY)
         .map_err(|e| e.to_string())?;
     
@@ -1060,65 +4982,432 @@ async fn get_video_frame(path: String, time_ms: f64) -> Result<String, String> {
 
 
 #[tauri::command]
-fn move_file(source: String, destination: String) -> Result<String, String> {
-    let src_path = Path::new(&source);
-    let dest_path = Path::new(&destination);
-
-    // 1. Check if source file exists
-    if !src_path.exists() {
-        return Err("Source file does not exist".to_string());
+fn move_file(source: String, destination: String) -> Result<String, String> {
+    let src_path = Path::new(&source);
+    let dest_path = Path::new(&destination);
+
+    // 1. Check if source file exists
+    if !src_path.exists() {
+        return Err("Source file does not exist".to_string());
+    }
+
+    // 2. Perform the copy and delete operation (move)
+    // fs::rename is the standard way to move files
+    match fs::rename(src_path, dest_path) {
+        Ok(_) => Ok("File transferred successfully".to_string()),
+        Err(e) => Err(format!("Failed to transfer file: {}", e)),
+    }
+}
+
+
+
+// The audio streams of a file, in the order `audioStream` counts them
+fn audio_streams(info: &MediaInfo) -> Vec<&StreamInfo> {
+    info.streams.iter().filter(|s| s.kind == "audio").collect()
+}
+
+fn validate_audio_selection(clip: &Clip, info: &MediaInfo) -> Result<(), String> {
+    let streams = audio_streams(info);
+    let index = clip.audio_stream.unwrap_or(0) as usize;
+    let stream = streams
+        .get(index)
+        .ok_or_else(|| format!("{}: stream de áudio {} não existe ({} disponíveis)", clip.name, index, streams.len()))?;
+    // ffprobe does not always report the channel count, then ffmpeg checks it itself
+    let Some(channels) = stream.channels else {
+        return Ok(());
+    };
+    if let Some(bad) = clip.audio_channels.iter().flatten().find(|c| **c >= channels) {
+        return Err(format!("{}: canal {} não existe no stream {} ({} canais)", clip.name, bad, index, channels));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn extract_audio(project_path: String, file_name: String, audio_stream: Option<u32>) -> Result<String, String> {
+    let video_path = Path::new(&project_path).join("videos").join(&file_name);
+    let output_folder = Path::new(&project_path).join("extracted_audios");
+    
+    // Create directory if it doesn't exist
+    if !output_folder.exists() {
+        fs::create_dir_all(&output_folder).map_err(|e| e.to_string())?;
+    }
+
+    // Output filename will be the same as input, but with .mp3 extension (for compatibility).
+    // Streams other than the first get their number in the name
+    let stem = Path::new(&file_name).file_stem().unwrap().to_str().unwrap();
+    let stream = audio_stream.unwrap_or(0);
+    let audio_file_name = if stream == 0 { format!("{}.mp3", stem) } else { format!("{}_a{}.mp3", stem, stream) };
+    let output_path = output_folder.join(&audio_file_name);
+
+    // If audio is already extracted, skip to improve performance
+    if output_path.exists() {
+        return Ok(audio_file_name);
+    }
+
+    // FFmpeg Command: -i (input), -map (the same stream the export reads), -vn (no video),
+    // -acodec libmp3lame (audio codec)
+    let status = Command::new("ffmpeg")
+        .arg("-i")
+        .arg(&video_path)
+        .arg("-map")
+        .arg(format!("0:a:{}", stream))
+        .arg("-vn")
+        .arg("-acodec")
+        .arg("libmp3lame")
+        .arg("-q:a")
+        .arg("2") // High quality setting
+        .arg(&output_path)
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    if status.status.success() {
+        Ok(audio_file_name)
+    } else {
+        let error = String::from_utf8_lossy(&status.stderr);
+        Err(format!("Error extracting audio: {}", error))
+    }
+}
+
+
+// Height of the proxies, enough for the viewer and light enough to scrub 4K/6K footage
+const PROXY_HEIGHT: u32 = 540;
+
+// Proxy of a video of the project: videos/<name> -> proxies/<name>.mp4
+fn proxy_path(source: &Path) -> Option<PathBuf> {
+    let videos = source.parent()?;
+    if videos.file_name()? != "videos" {
+        return None;
+    }
+    let name = source.file_name()?.to_string_lossy();
+    Some(videos.parent()?.join("proxies").join(format!("{}.mp4", name)))
+}
+
+// The proxy to read instead of `source` while editing, if it was generated after the last
+// change of the source
+fn ready_proxy_for(source: &Path) -> Option<PathBuf> {
+    let proxy = proxy_path(source)?;
+    let proxy_modified = fs::metadata(&proxy).and_then(|m| m.modified()).ok()?;
+    let source_modified = fs::metadata(source).and_then(|m| m.modified()).ok()?;
+    (proxy_modified >= source_modified).then_some(proxy)
+}
+
+// The original video of a proxy path
+fn original_for_proxy(path: &Path) -> Option<PathBuf> {
+    let proxies = path.parent()?;
+    if proxies.file_name()? != "proxies" {
+        return None;
+    }
+    let name = path.file_name()?.to_string_lossy();
+    let original = proxies.parent()?.join("videos").join(name.strip_suffix(".mp4")?);
+    original.is_file().then_some(original)
+}
+
+fn build_proxy_args(source: &Path, output: &Path, height: u32) -> Vec<String> {
+    vec![
+        "-y".into(),
+        "-hide_banner".into(),
+        "-progress".into(), "pipe:1".into(),
+        "-nostats".into(),
+        "-i".into(), source.to_string_lossy().into_owned(),
+        "-map".into(), "0:v:0".into(),
+        "-map".into(), "0:a?".into(),
+        // Same timestamps as the source so times in the timeline match, short GOP for seeking
+        "-fps_mode".into(), "passthrough".into(),
+        "-vf".into(), format!("scale=-2:{}", height),
+        "-c:v".into(), "libx264".into(),
+        "-preset".into(), "veryfast".into(),
+        "-crf".into(), "23".into(),
+        "-g".into(), "15".into(),
+        "-pix_fmt".into(), "yuv420p".into(),
+        "-c:a".into(), "aac".into(),
+        "-b:a".into(), "128k".into(),
+        "-movflags".into(), "+faststart".into(),
+        "-f".into(), "mp4".into(),
+        output.to_string_lossy().into_owned(),
+    ]
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ProxyUpdate {
+    file_name: String,
+    status: ExportJobStatus,
+    progress: u32,
+    proxy_path: Option<String>,
+    error: Option<String>,
+}
+
+fn emit_proxy_update(app_handle: &tauri::AppHandle, source: &Path, status: ExportJobStatus, progress: u32, error: Option<String>) {
+    let update = ProxyUpdate {
+        file_name: source.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+        status,
+        progress,
+        proxy_path: (status == ExportJobStatus::Done)
+            .then(|| proxy_path(source).map(|p| p.to_string_lossy().into_owned()))
+            .flatten(),
+        error,
+    };
+    let _ = app_handle.emit("proxy-job", update);
+}
+
+// Queues proxies for videos of the project (all of them without `file_names`) and starts the
+// background task. Videos already small enough or with an up to date proxy are skipped.
+// Returns the names of the files queued; progress comes in `proxy-job` events.
+#[tauri::command]
+async fn generate_proxies(
+    app_handle: tauri::AppHandle,
+    project_path: String,
+    file_names: Option<Vec<String>>,
+    force: Option<bool>,
+) -> Result<Vec<String>, String> {
+    let videos_dir = Path::new(&project_path).join("videos");
+    let names = match file_names {
+        Some(names) => names,
+        None => list_assets(project_path.clone())?,
+    };
+    fs::create_dir_all(Path::new(&project_path).join("proxies")).map_err(|e| format!("Erro ao criar proxies: {}", e))?;
+
+    let mut queued = Vec::new();
+    for name in names {
+        let source = videos_dir.join(&name);
+        if !source.is_file() || (!force.unwrap_or(false) && ready_proxy_for(&source).is_some()) {
+            continue;
+        }
+        let info = match probe_media_with_cache(&app_handle, Some(Path::new(&project_path)), &source.to_string_lossy()) {
+            Ok(info) => info,
+            Err(_) => continue,
+        };
+        // Only videos bigger than the proxy
+        let is_video = info.is_video();
+        let needs_proxy = info.video_stream().and_then(|v| v.width.zip(v.height)).map(|(w, h)| w.min(h) > PROXY_HEIGHT).unwrap_or(false);
+        if !is_video || !needs_proxy {
+            continue;
+        }
+
+        let state = app_handle.state::<ProxyState>();
+        let mut proxies = state.0.lock().unwrap();
+        let running = proxies.running.as_ref().map(|(path, _)| path == &source).unwrap_or(false);
+        if !running && !proxies.pending.contains(&source) {
+            proxies.pending.push(source.clone());
+            emit_proxy_update(&app_handle, &source, ExportJobStatus::Queued, 0, None);
+            queued.push(name);
+        }
+    }
+
+    let start_worker = {
+        let state = app_handle.state::<ProxyState>();
+        let mut proxies = state.0.lock().unwrap();
+        let start = !proxies.worker && !proxies.pending.is_empty();
+        proxies.worker |= start;
+        start
+    };
+    if start_worker {
+        let handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            run_proxy_worker(&handle).await;
+        });
+    }
+
+    Ok(queued)
+}
+
+// Encodes the pending proxies one after the other until the queue is empty
+async fn run_proxy_worker(app_handle: &tauri::AppHandle) {
+    loop {
+        let next = {
+            let state = app_handle.state::<ProxyState>();
+            let mut proxies = state.0.lock().unwrap();
+            if proxies.pending.is_empty() {
+                proxies.worker = false;
+                return;
+            }
+            proxies.cancelled = false;
+            proxies.pending.remove(0)
+        };
+        run_proxy(app_handle, &next).await;
+    }
+}
+
+// Encodes one proxy into a .part file, renamed when complete so a half written proxy is
+// never picked up in place of the original
+async fn run_proxy(app_handle: &tauri::AppHandle, source: &Path) {
+    let Some(output) = proxy_path(source) else { return };
+    let part = output.with_extension("mp4.part");
+    // Sources are in videos/ of the project, whose media_info.json may already have them
+    let duration = probe_media_with_cache(app_handle, source.parent().and_then(Path::parent), &source.to_string_lossy())
+        .map(|info| info.duration)
+        .unwrap_or(0.0);
+
+    let spawned = app_handle
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| format!("Sidecar não encontrado: {}", e))
+        .and_then(|command| {
+            command
+                .args(build_proxy_args(source, &part, PROXY_HEIGHT))
+                .spawn()
+                .map_err(|e| format!("Falha ao iniciar o FFmpeg: {}", e))
+        });
+    let (mut rx, child) = match spawned {
+        Ok(spawned) => spawned,
+        Err(e) => {
+            emit_proxy_update(app_handle, source, ExportJobStatus::Failed, 0, Some(e));
+            return;
+        }
+    };
+    // cancel_proxies may have run while the process was starting, with nothing to kill yet
+    {
+        let state = app_handle.state::<ProxyState>();
+        let mut proxies = state.0.lock().unwrap();
+        if proxies.cancelled {
+            drop(proxies);
+            let _ = child.kill();
+            let _ = fs::remove_file(&part);
+            emit_proxy_update(app_handle, source, ExportJobStatus::Cancelled, 0, None);
+            return;
+        }
+        proxies.running = Some((source.to_path_buf(), child));
+    }
+    emit_proxy_update(app_handle, source, ExportJobStatus::Running, 0, None);
+
+    let mut last_percent = None;
+    let mut stderr_tail: Vec<String> = Vec::new();
+    while let Some(event) = rx.recv().await {
+        match event {
+            tauri_plugin_shell::process::CommandEvent::Stdout(line_bytes) => {
+                for line in String::from_utf8_lossy(&line_bytes).lines() {
+                    if let Some(percent) = parse_progress_line(line, duration) {
+                        if last_percent != Some(percent) {
+                            last_percent = Some(percent);
+                            emit_proxy_update(app_handle, source, ExportJobStatus::Running, percent, None);
+                        }
+                    }
+                }
+            }
+            tauri_plugin_shell::process::CommandEvent::Stderr(line_bytes) => {
+                stderr_tail.extend(String::from_utf8_lossy(&line_bytes).lines().map(|l| l.to_string()));
+                if stderr_tail.len() > 20 {
+                    stderr_tail.drain(..stderr_tail.len() - 20);
+                }
+            }
+            tauri_plugin_shell::process::CommandEvent::Terminated(status) => {
+                let cancelled = {
+                    let state = app_handle.state::<ProxyState>();
+                    let mut proxies = state.0.lock().unwrap();
+                    proxies.running = None;
+                    proxies.cancelled
+                };
+                if status.code == Some(0) {
+                    match fs::rename(&part, &output) {
+                        Ok(()) => emit_proxy_update(app_handle, source, ExportJobStatus::Done, 100, None),
+                        Err(e) => emit_proxy_update(app_handle, source, ExportJobStatus::Failed, 0, Some(e.to_string())),
+                    }
+                } else {
+                    let _ = fs::remove_file(&part);
+                    // Killed by cancel_proxies (exit code 1 on Windows, hence the flag)
+                    if status.signal.is_some() || cancelled {
+                        emit_proxy_update(app_handle, source, ExportJobStatus::Cancelled, 0, None);
+                    } else {
+                        emit_proxy_update(app_handle, source, ExportJobStatus::Failed, 0, Some(stderr_tail.join("\n")));
+                    }
+                }
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+// Drops the pending proxies and stops the one being encoded
+#[tauri::command]
+fn cancel_proxies(app_handle: tauri::AppHandle) {
+    let state = app_handle.state::<ProxyState>();
+    let mut proxies = state.0.lock().unwrap();
+    for source in proxies.pending.drain(..) {
+        emit_proxy_update(&app_handle, &source, ExportJobStatus::Cancelled, 0, None);
     }
-
-    // 2. Perform the copy and delete operation (move)
-    // fs::rename is the standard way to move files
-    match fs::rename(src_path, dest_path) {
-        Ok(_) => Ok("File transferred successfully".to_string()),
-        Err(e) => Err(format!("Failed to transfer file: {}", e)),
+    proxies.cancelled = true;
+    if let Some((_, child)) = proxies.running.take() {
+        let _ = child.kill();
     }
 }
 
+// Names of the videos of the project that have an up to date proxy
+#[tauri::command]
+fn list_proxies(project_path: String) -> Result<Vec<String>, String> {
+    let videos_dir = Path::new(&project_path).join("videos");
+    Ok(list_assets(project_path)?
+        .into_iter()
+        .filter(|name| ready_proxy_for(&videos_dir.join(name)).is_some())
+        .collect())
+}
 
-
+// Splits the audio of a file into new assets in videos/: one WAV per stream
+// (<name>_a<stream>.wav) or, with `split_channels`, one mono WAV per channel
+// (<name>_a<stream>_c<channel>.wav). Returns the names of the files written.
 #[tauri::command]
-async fn extract_audio(project_path: String, file_name: String) -> Result<String, String> {
-    let video_path = Path::new(&project_path).join("videos").join(&file_name);
-    let output_folder = Path::new(&project_path).join("extracted_audios");
-    
-    // Create directory if it doesn't exist
-    if !output_folder.exists() {
-        fs::create_dir_all(&output_folder).map_err(|e| e.to_string())?;
+async fn split_audio_streams(
+    app_handle: tauri::AppHandle,
+    project_path: String,
+    file_name: String,
+    split_channels: Option<bool>,
+) -> Result<Vec<String>, String> {
+    let videos_dir = Path::new(&project_path).join("videos");
+    let source = videos_dir.join(&file_name);
+    let info = probe_media_with_cache(&app_handle, Some(Path::new(&project_path)), &source.to_string_lossy())?;
+    let streams = audio_streams(&info);
+    if streams.is_empty() {
+        return Err(format!("{} não tem áudio", file_name));
     }
 
-    // Output filename will be the same as input, but with .mp3 extension (for compatibility)
-    let audio_file_name = format!("{}.mp3", Path::new(&file_name).file_stem().unwrap().to_str().unwrap());
-    let output_path = output_folder.join(&audio_file_name);
-
-    // If audio is already extracted, skip to improve performance
-    if output_path.exists() {
-        return Ok(audio_file_name);
+    let stem = Path::new(&file_name).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let mut args: Vec<String> = vec!["-y".into(), "-hide_banner".into(), "-i".into(), source.to_string_lossy().into_owned()];
+    let mut outputs = Vec::new();
+    for (index, stream) in streams.iter().enumerate() {
+        // Keep 24 bit sources at 24 bit
+        let codec = if stream.bit_depth.unwrap_or(16) > 16 { "pcm_s24le" } else { "pcm_s16le" };
+        let channels = stream.channels.unwrap_or(1);
+        if split_channels.unwrap_or(false) && channels > 1 {
+            for channel in 0..channels {
+                let name = format!("{}_a{}_c{}.wav", stem, index, channel);
+                args.extend([
+                    "-map".into(), format!("0:a:{}", index),
+                    "-af".into(), format!("pan=mono|c0=c{}", channel),
+                    "-c:a".into(), codec.into(),
+                    videos_dir.join(&name).to_string_lossy().into_owned(),
+                ]);
+                outputs.push(name);
+            }
+        } else {
+            let name = format!("{}_a{}.wav", stem, index);
+            args.extend([
+                "-map".into(), format!("0:a:{}", index),
+                "-c:a".into(), codec.into(),
+                videos_dir.join(&name).to_string_lossy().into_owned(),
+            ]);
+            outputs.push(name);
+        }
     }
 
-    // FFmpeg Command: -i (input), -vn (no video), -acodec libmp3lame (audio codec)
-    let status = Command::new("ffmpeg")
-        .arg("-i")
-        .arg(&video_path)
-        .arg("-vn")
-        .arg("-acodec")
-        .arg("libmp3lame")
-        .arg("-q:a")
-        .arg("2") // High quality setting
-        .arg(&output_path)
+    let output = app_handle
+        .shell()
+        .sidecar("ffmpeg")
+        .map_err(|e| format!("Sidecar não encontrado: {}", e))?
+        .args(args)
         .output()
-        .map_err(|e| e.to_string())?;
-
-    if status.status.success() {
-        Ok(audio_file_name)
-    } else {
-        let error = String::from_utf8_lossy(&status.stderr);
-        Err(format!("Error extracting audio: {}", error))
+        .await
+        .map_err(|e| format!("Falha ao iniciar o FFmpeg: {}", e))?;
+    if !output.status.success() {
+        for name in &outputs {
+            let _ = fs::remove_file(videos_dir.join(name));
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let lines: Vec<&str> = stderr.lines().collect();
+        return Err(format!("Erro ao separar o áudio: {}", lines[lines.len().saturating_sub(10)..].join("\n")));
     }
-}
 
+    Ok(outputs)
+}
 
 #[tauri::command]
 async fn get_waveform_data(path: String, samples: usize) -> Result<Vec<f32>, String> {
@@ -1182,9 +5471,11 @@ fn main() {
     thread::spawn(move || {
     let server = Server::http("127.0.0.1:1234").unwrap();
     for request in server.incoming_requests() {
-        let url = request.url().trim_start_matches('/');
+        // `?original` skips the proxy of a video
+        let (url, query) = request.url().trim_start_matches('/').split_once('?').unwrap_or((request.url().trim_start_matches('/'), ""));
         let decoded_path = percent_encoding::percent_decode_str(url).decode_utf8_lossy().into_owned();
-        let path = Path::new(&decoded_path);
+        let proxy = if query.split('&').any(|q| q == "original") { None } else { ready_proxy_for(Path::new(&decoded_path)) };
+        let path = proxy.as_deref().unwrap_or(Path::new(&decoded_path));
 
         if path.exists() && path.is_file() {
             let mut file = File::open(&path).unwrap();
@@ -1239,12 +5530,19 @@ fn main() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init()) // Dialog plugin for system file pickers
         // Custom protocol for serving local video files with range-request support
-        .manage(ExportState(Mutex::new(None)))
+        .manage(ExportState(Mutex::new(ExportQueue::default())))
+        .manage(ProxyState(Mutex::new(ProxyQueue::default())))
+        .manage(MediaInfoState(Mutex::new(())))
+        .manage(AssetsState(Mutex::new(())))
         .invoke_handler(tauri::generate_handler![
             create_project_folder, 
             list_projects, 
             delete_project, 
             import_asset, 
+            conform_asset,
+            detect_image_sequences,
+            import_image_sequence,
+            list_asset_records,
             list_assets, 
             download_youtube_video, 
             load_latest_project, 
@@ -1258,9 +5556,24 @@ fn main() {
             delete_file, 
             get_video_frame, 
             extract_audio, 
+            split_audio_streams,
             get_waveform_data,
             export_video,
             cancel_export,
+            bake_ducking_keyframes,
+            list_export_jobs,
+            set_export_concurrency,
+            remove_export_job,
+            load_export_queue,
+            render_preview,
+            clear_preview_cache,
+            probe_media,
+            generate_proxies,
+            cancel_proxies,
+            list_proxies,
+            list_export_presets,
+            save_export_preset,
+            delete_export_preset,
             move_file,
             copy_file,
             load_project_config,
@@ -1271,4 +5584,928 @@ fn main() {
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f64, value: f64, interpolation: Interpolation) -> Keyframe {
+        Keyframe { id: format!("k{}", time), time, value, interpolation, bezier: None }
+    }
+
+    #[test]
+    fn speed_pts_expression_constant_speed() {
+        let expr = build_speed_pts_expression(&[keyframe(0.0, 2.0, Interpolation::Linear)]);
+        assert_eq!(expr, "0.000000+(T-0.000000)/2.000000");
+    }
+
+    #[test]
+    fn speed_pts_expression_switches_segment_at_source_time() {
+        let expr = build_speed_pts_expression(&[
+            keyframe(0.0, 1.0, Interpolation::Hold),
+            keyframe(2.0, 2.0, Interpolation::Linear),
+        ]);
+        assert_eq!(
+            expr,
+            "if(lt(T,2.000000),0.000000+(T-0.000000)/1.000000,2.000000+(T-2.000000)/2.000000)"
+        );
+    }
+
+    #[test]
+    fn speed_pts_expression_solves_ramps() {
+        let expr = build_speed_pts_expression(&[
+            keyframe(0.0, 1.0, Interpolation::Linear),
+            keyframe(2.0, 3.0, Interpolation::Linear),
+        ]);
+        assert!(expr.starts_with("if(lt(T,4.000000),0.000000+(sqrt(max(0,"));
+    }
+
+    #[test]
+    fn atempo_chain_within_range() {
+        assert_eq!(build_atempo_chain(2.0), "atempo=2.000000");
+        assert_eq!(build_atempo_chain(0.5), "atempo=0.500000");
+    }
+
+    #[test]
+    fn atempo_chain_splits_slow_speeds() {
+        assert_eq!(build_atempo_chain(0.25), "atempo=0.5,atempo=0.500000");
+
+        // Below MIN_SPEED it is clamped; the product of the chain is the tempo
+        let chain = build_atempo_chain(0.001);
+        let product: f64 = chain
+            .split(',')
+            .map(|f| f.trim_start_matches("atempo=").parse::<f64>().unwrap())
+            .product();
+        assert!((product - MIN_SPEED).abs() < 1e-6);
+        assert!(chain.split(',').all(|f| f.trim_start_matches("atempo=").parse::<f64>().unwrap() >= 0.5));
+    }
+
+    #[test]
+    fn progress_lines_become_percentages() {
+        assert_eq!(parse_progress_line("out_time_us=5000000", 10.0), Some(50));
+        assert_eq!(parse_progress_line("  out_time_us=0\n", 10.0), Some(0));
+        // 100 is only reached when ffmpeg exits
+        assert_eq!(parse_progress_line("out_time_us=20000000", 10.0), Some(99));
+        assert_eq!(parse_progress_line("out_time_us=N/A", 10.0), None);
+        assert_eq!(parse_progress_line("frame=12", 10.0), None);
+        assert_eq!(parse_progress_line("out_time_us=5000000", 0.0), None);
+    }
+
+    fn clip(value: serde_json::Value) -> Clip {
+        let mut base = serde_json::json!({
+            "id": "c", "name": "a.mp4", "path": "/p/a.mp4", "start": 0.0, "duration": 10.0,
+            "beginmoment": 0.0, "trackId": "1", "type": "video"
+        });
+        base.as_object_mut().unwrap().extend(value.as_object().unwrap().clone());
+        serde_json::from_value(base).unwrap()
+    }
+
+    #[test]
+    fn transitions_pair_adjacent_clips_of_a_track() {
+        let clips = vec![
+            clip(serde_json::json!({ "id": "a", "start": 0.0, "duration": 5.0 })),
+            clip(serde_json::json!({ "id": "b", "start": 5.0, "duration": 5.0, "transitionIn": { "duration": 1.0 } })),
+        ];
+        let plan = plan_transitions(&clips);
+        assert_eq!(plan[0].next, Some(1));
+        assert_eq!(plan[1].prev, Some(0));
+        assert_eq!(plan[0].post, 0.5);
+        assert_eq!(plan[1].pre, 0.5);
+    }
+
+    #[test]
+    fn transitions_are_limited_by_the_shorter_clip() {
+        let clips = vec![
+            clip(serde_json::json!({ "id": "a", "start": 0.0, "duration": 2.0 })),
+            clip(serde_json::json!({ "id": "b", "start": 2.0, "duration": 3.0, "transitionIn": { "duration": 4.0 } })),
+        ];
+        let plan = plan_transitions(&clips);
+        assert_eq!(plan[1].pre, 1.0);
+    }
+
+    #[test]
+    fn transitions_ignore_other_tracks_and_gaps() {
+        let clips = vec![
+            clip(serde_json::json!({ "id": "a", "start": 0.0, "duration": 5.0, "trackId": "2" })),
+            clip(serde_json::json!({ "id": "b", "start": 5.0, "duration": 5.0, "transitionIn": { "duration": 1.0 } })),
+            clip(serde_json::json!({ "id": "c", "start": 11.0, "duration": 5.0, "transitionIn": { "duration": 1.0 } })),
+        ];
+        let plan = plan_transitions(&clips);
+        assert!(plan.iter().all(|edges| edges.prev.is_none() && edges.next.is_none()));
+    }
+
+    #[test]
+    fn filter_values_are_escaped_for_option_and_graph() {
+        assert_eq!(escape_filter_value("plain"), "plain");
+        assert_eq!(escape_filter_value("a:b"), r"a\\:b");
+        assert_eq!(escape_filter_value("it's"), r"it\\\'s");
+        assert_eq!(escape_filter_value("[x],y;"), r"\[x\]\,y\;");
+        assert_eq!(escape_filter_value(r"C:\f"), r"C\\:\\\\f");
+    }
+
+    #[test]
+    fn loudnorm_measurement_is_read_from_the_end_of_stderr() {
+        let stderr = "[Parsed_loudnorm_0 @ 0x1] \n{\n\t\"input_i\" : \"-23.54\",\n\t\"input_tp\" : \"-7.96\",\n\t\"input_lra\" : \"0.00\",\n\t\"input_thresh\" : \"-34.17\",\n\t\"output_i\" : \"-14.00\",\n\t\"target_offset\" : \"0.01\"\n}\n";
+        let measured = parse_loudnorm_output(stderr).unwrap();
+        assert_eq!(measured.input_i, "-23.54");
+        assert_eq!(measured.input_tp, "-7.96");
+        assert_eq!(measured.input_lra, "0.00");
+        assert_eq!(measured.input_thresh, "-34.17");
+        assert_eq!(measured.target_offset, "0.01");
+
+        assert!(parse_loudnorm_output("no measurement here").is_none());
+    }
+
+    #[test]
+    fn export_presets_are_checked_against_the_container() {
+        let preset = |container: &str, video: &str, audio: &str| ExportPreset {
+            container: container.to_string(),
+            video_codec: video.to_string(),
+            audio_codec: audio.to_string(),
+            ..ExportPreset::default()
+        };
+        assert!(validate_export_preset(&ExportPreset::default()).is_ok());
+        assert!(validate_export_preset(&preset("mov", "prores", "pcm")).is_ok());
+        assert!(validate_export_preset(&preset("webm", "vp9", "opus")).is_ok());
+        assert!(validate_export_preset(&preset("mkv", "dnxhr", "opus")).is_ok());
+
+        assert!(validate_export_preset(&preset("mp4", "prores", "aac")).is_err());
+        assert!(validate_export_preset(&preset("mp4", "h264", "pcm")).is_err());
+        assert!(validate_export_preset(&preset("webm", "h264", "opus")).is_err());
+        assert!(validate_export_preset(&preset("avi", "h264", "aac")).is_err());
+        assert!(validate_export_preset(&preset("mp4", "mpeg2", "aac")).is_err());
+        assert!(validate_export_preset(&preset("mp4", "h264", "mp3")).is_err());
+    }
+
+    #[test]
+    fn clip_range_cuts_and_moves_clips() {
+        let clips = vec![
+            clip(serde_json::json!({ "id": "a", "start": 0.0, "duration": 10.0, "beginmoment": 1.0 })),
+            clip(serde_json::json!({ "id": "b", "start": 12.0, "duration": 3.0 })),
+            clip(serde_json::json!({ "id": "c", "start": 4.0, "duration": 2.0 })),
+        ];
+        let cut = clip_range(&clips, 2.0, 8.0);
+        assert_eq!(cut.len(), 2);
+        assert_eq!((cut[0].id.as_str(), cut[0].start, cut[0].duration, cut[0].beginmoment), ("a", 0.0, 6.0, 3.0));
+        assert_eq!((cut[1].id.as_str(), cut[1].start, cut[1].duration, cut[1].beginmoment), ("c", 2.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn clip_range_keeps_the_fade_curves() {
+        let clips = vec![clip(serde_json::json!({
+            "start": 0.0, "duration": 10.0, "fadein": 2.0, "fadeout": 2.0, "fadeinAudio": 0.5, "fadeoutAudio": 0.5
+        }))];
+        let cut = clip_range(&clips, 1.0, 9.5);
+        let c = &cut[0];
+        assert_eq!((c.fadein, c.fadeout), (Some(2.0), Some(2.0)));
+        assert_eq!((c.fade_cut.video_in, c.fade_cut.video_out), (1.0, 0.5));
+        // Fades that were cut off entirely are dropped
+        assert_eq!((c.fadeinAudio, c.fadeoutAudio), (None, None));
+
+        let fades = build_video_fades(c, "");
+        assert_eq!(fades[0], "setpts=PTS+1.0000/TB,fade=t=in:st=0:d=2.0000,setpts=PTS-1.0000/TB");
+        // The fade out still starts 8 s into the original clip, 7 s into the cut one
+        assert_eq!(fades[1], "fade=t=out:st=7.0000:d=2.0000");
+    }
+
+    #[test]
+    fn clip_range_follows_speed_and_drops_cut_transitions() {
+        let clips = vec![
+            clip(serde_json::json!({
+                "start": 0.0, "duration": 10.0,
+                "keyframes": { "speed": [{ "id": "s", "time": 0.0, "value": 2.0 }] },
+                "transitionIn": { "duration": 1.0 }
+            })),
+        ];
+        let cut = clip_range(&clips, 3.0, 10.0);
+        assert_eq!(cut[0].beginmoment, 6.0);
+        assert!(cut[0].transition_in.is_none());
+        let speed = cut[0].keyframes.as_ref().unwrap().speed.as_ref().unwrap();
+        assert_eq!((speed[0].time, speed[0].value), (0.0, 2.0));
+    }
+
+    #[test]
+    fn shift_keyframes_moves_every_track() {
+        let mut keyframes: Keyframes = serde_json::from_value(serde_json::json!({
+            "volume": [{ "id": "v", "time": 3.0, "value": 1.0 }],
+            "positionX": [{ "id": "x", "time": 5.0, "value": 10.0 }],
+            "rotation3d": [{ "id": "r", "time": 4.0, "value": { "rot": 0.0, "rot3d": 30.0 } }],
+            "position": [{ "id": "p", "time": 2.0, "value": { "x": 1.0, "y": 2.0 } }]
+        }))
+        .unwrap();
+        shift_keyframes(&mut keyframes, 2.5);
+        assert_eq!(keyframes.volume.unwrap()[0].time, 0.5);
+        assert_eq!(keyframes.position_x.unwrap()[0].time, 2.5);
+        assert_eq!(keyframes.rotation3d.unwrap()[0].time, 1.5);
+        assert_eq!(keyframes.position.unwrap()[0].time, -0.5);
+        assert!(keyframes.opacity.is_none());
+    }
+
+    #[test]
+    fn vfrdet_ratio_is_read_from_its_last_report() {
+        let stderr = "frame=  988 fps=0.0 q=-0.0 Lsize=N/A\n[Parsed_vfrdet_0 @ 0x55d0] VFR:0.123456 (12/988) min: 1 max: 2 avg: 1\n";
+        assert_eq!(parse_vfrdet_output(stderr), Some(0.123456));
+        assert_eq!(parse_vfrdet_output("[Parsed_vfrdet_0 @ 0x1] VFR:0.000000 (0/300)"), Some(0.0));
+        assert_eq!(parse_vfrdet_output("no report"), None);
+    }
+
+    fn sequence(pattern: &str) -> ImageSequence {
+        ImageSequence {
+            name: "shot".to_string(),
+            directory: String::new(),
+            pattern: pattern.to_string(),
+            start_number: 1,
+            frame_count: 10,
+            frame_rate: 24.0,
+            width: None,
+            height: None,
+        }
+    }
+
+    #[test]
+    fn frame_names_are_split_into_prefix_number_and_extension() {
+        assert_eq!(split_frame_name("shot_0012.exr"), Some(("shot_", "0012", "exr")));
+        assert_eq!(split_frame_name("0001.PNG"), Some(("", "0001", "PNG")));
+        assert_eq!(split_frame_name("take2_v10.tif"), Some(("take2_v", "10", "tif")));
+        assert_eq!(split_frame_name("shot.exr"), None);
+        assert_eq!(split_frame_name("shot_0012.mp4"), None);
+        assert_eq!(split_frame_name("noextension"), None);
+    }
+
+    #[test]
+    fn image_sequences_are_found_in_a_folder() {
+        let dir = std::env::temp_dir().join(format!("image_sequences_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let touch = |name: String| fs::write(dir.join(name), b"").unwrap();
+        // 1..=5 then a gap, where ffmpeg would stop
+        for n in [1, 2, 3, 4, 5, 7] {
+            touch(format!("shot_{:04}.png", n));
+        }
+        for n in [8, 9, 10, 11] {
+            touch(format!("take_{}.exr", n));
+        }
+        touch("single_0001.png".to_string());
+        touch("notes.txt".to_string());
+
+        let sequences = find_image_sequences(&dir, 24.0);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(sequences.len(), 2);
+        assert_eq!((sequences[0].pattern.as_str(), sequences[0].start_number, sequences[0].frame_count), ("shot_%04d.png", 1, 5));
+        assert_eq!(sequences[0].name, "shot");
+        assert_eq!((sequences[1].pattern.as_str(), sequences[1].start_number, sequences[1].frame_count), ("take_%d.exr", 8, 4));
+        assert_eq!(sequences[1].frame_rate, 24.0);
+    }
+
+    #[test]
+    fn image_sequence_frame_names_fill_the_pattern() {
+        assert_eq!(image_sequence_frame_name(&sequence("shot_%04d.exr"), 12), "shot_0012.exr");
+        assert_eq!(image_sequence_frame_name(&sequence("shot_%04d.exr"), 12345), "shot_12345.exr");
+        assert_eq!(image_sequence_frame_name(&sequence("f_%d.png"), 7), "f_7.png");
+        assert_eq!(image_sequence_frame_name(&sequence("%03d.png"), 5), "005.png");
+    }
+
+    #[test]
+    fn channel_selection_builds_a_stereo_pan() {
+        assert_eq!(build_channel_selection(&[]), None);
+        assert_eq!(build_channel_selection(&[2]).unwrap(), "pan=stereo|c0=c2|c1=c2");
+        assert_eq!(build_channel_selection(&[1, 0]).unwrap(), "pan=stereo|c0=c1|c1=c0");
+        assert_eq!(
+            build_channel_selection(&[0, 1, 2, 3]).unwrap(),
+            "pan=stereo|c0=0.2500*c0+0.2500*c1+0.2500*c2+0.2500*c3|c1=0.2500*c0+0.2500*c1+0.2500*c2+0.2500*c3"
+        );
+    }
+
+    #[test]
+    fn color_filter_is_empty_for_neutral_grading() {
+        assert_eq!(build_color_filter(&clip(serde_json::json!({})), 0, &ProjectSettings::default(), 0.0), None);
+        assert_eq!(build_color_filter(&clip(serde_json::json!({ "colorCorrection": { "gamma": 1.0, "saturation": 1.0 } })), 0, &ProjectSettings::default(), 0.0), None);
+    }
+
+    #[test]
+    fn color_filter_mixes_channel_gains() {
+        let graded = clip(serde_json::json!({ "colorCorrection": { "exposure": 1.0, "temperature": 0.4 } }));
+        assert_eq!(
+            build_color_filter(&graded, 0, &ProjectSettings::default(), 0.0).unwrap(),
+            "colorchannelmixer=rr=2.2000:gg=2.0000:bb=1.8000"
+        );
+    }
+
+    #[test]
+    fn color_filter_wheels_become_a_clipped_lutrgb_curve() {
+        let graded = clip(serde_json::json!({ "colorCorrection": { "contrast": 1.2, "lift": 0.1, "gamma": 2.0, "gain": 0.9 } }));
+        let curve = "'clip(maxval*pow(clip(0.9000*clip((val/maxval-0.5)*1.2000+0.5,0,1)+0.1000*(1-clip((val/maxval-0.5)*1.2000+0.5,0,1)),0,1),1/2.0000),minval,maxval)'";
+        assert_eq!(
+            build_color_filter(&graded, 0, &ProjectSettings::default(), 0.0).unwrap(),
+            format!("lutrgb=r={c}:g={c}:b={c}", c = curve)
+        );
+    }
+
+    #[test]
+    fn color_filter_saturation_and_lut_come_last() {
+        let graded = clip(serde_json::json!({
+            "colorCorrection": { "saturation": 1.5, "gain": 1.1, "lut": "luts/Look:A.cube" }
+        }));
+        let filter = build_color_filter(&graded, 0, &ProjectSettings::default(), 0.0).unwrap();
+        assert!(filter.starts_with("lutrgb="));
+        assert!(filter.ends_with(r",hue=s=1.5000,lut3d=file=luts/Look\\:A.cube"));
+    }
+
+    #[test]
+    fn text_filter_centers_white_text_by_default() {
+        let title = clip(serde_json::json!({ "type": "text", "path": "", "text": { "content": "Hello\n" } }));
+        assert_eq!(
+            build_text_filter(&title, &ProjectSettings::default()),
+            "format=rgba,drawtext=text=Hello:expansion=none:fontsize=90.00:fontcolor=white:x=(w-text_w)/2:y=(h-text_h)/2"
+        );
+
+        let empty = clip(serde_json::json!({ "type": "text", "path": "" }));
+        assert_eq!(build_text_filter(&empty, &ProjectSettings::default()), "format=rgba");
+    }
+
+    #[test]
+    fn text_filter_places_and_styles_the_text() {
+        let title = clip(serde_json::json!({
+            "type": "text", "path": "",
+            "text": {
+                "content": "Time: 10%", "fontSize": 64.0, "color": "#FFCC00",
+                "align": "right", "verticalAlign": "bottom", "margin": 40.0,
+                "outlineWidth": 3.0, "boxColor": "#000000AA", "boxPadding": 12.0
+            }
+        }));
+        assert_eq!(
+            build_text_filter(&title, &ProjectSettings::default()),
+            concat!(
+                r"format=rgba,drawtext=text=Time\\: 10%:expansion=none:fontsize=64.00:fontcolor=0xFFCC00",
+                ":x=w-text_w-40.00:y=h-text_h-40.00:borderw=3.00:bordercolor=black",
+                ":box=1:boxcolor=0x000000AA:boxborderw=12"
+            )
+        );
+    }
+
+    #[test]
+    fn text_clips_read_a_transparent_canvas() {
+        let title = clip(serde_json::json!({ "type": "text", "path": "", "duration": 4.0 }));
+        let args = build_input_args(&[title], &ProjectSettings::default());
+        assert_eq!(
+            args[args.len() - 4..],
+            ["-f", "lavfi", "-i", "color=c=black@0:s=1920x1080:r=30:d=4.0000"]
+        );
+    }
+
+    #[test]
+    fn looped_animations_read_the_clip_and_its_handle() {
+        let clips = vec![
+            clip(serde_json::json!({ "id": "a", "path": "/p/a.gif", "start": 0.0, "duration": 4.0, "beginmoment": 1.0 })),
+            clip(serde_json::json!({ "id": "b", "start": 4.0, "duration": 4.0, "transitionIn": { "duration": 1.0 } })),
+            // A long timeline must not make the animation read longer
+            clip(serde_json::json!({ "id": "c", "start": 600.0, "duration": 4.0, "trackId": "2" })),
+        ];
+        let args = build_input_args(&clips, &ProjectSettings::default());
+        let gif = args.iter().position(|a| a == "/p/a.gif").unwrap();
+        assert_eq!(args[gif - 5..gif], ["-stream_loop", "-1", "-t", "5.5000", "-i"]);
+    }
+
+    #[test]
+    fn animations_without_loop_just_end() {
+        let clips = vec![
+            clip(serde_json::json!({ "path": "/p/a.gif", "loop": false })),
+            clip(serde_json::json!({ "path": "/p/frames.ffconcat", "start": 20.0 })),
+        ];
+        let args = build_input_args(&clips, &ProjectSettings::default());
+        assert_eq!(args[5..], ["-i", "/p/a.gif", "-f", "concat", "-safe", "0", "-stream_loop", "-1", "-t", "10.0000", "-i", "/p/frames.ffconcat"]);
+    }
+
+    #[test]
+    fn ducking_follows_the_dialogue_above_the_threshold() {
+        let ducking = Ducking { enabled: Some(true), threshold: Some(-30.0), ratio: Some(6.0), attack: Some(20.0), release: Some(300.0) };
+        let mut levels: Vec<(f64, f64)> = (0..20).map(|n| (n as f64 * 0.05, -10.0)).collect();
+        levels.extend((20..40).map(|n| (n as f64 * 0.05, -60.0)));
+        let reduction = ducking_gain_reduction(&levels, &ducking);
+
+        assert_eq!(reduction[0].1, 0.0);
+        // (-10 - -30) * (1 - 1/6) once the attack is over
+        assert!((reduction[19].1 - 20.0 * 5.0 / 6.0).abs() < 1e-3);
+        // The release is slower than the attack, the music comes back gradually
+        assert!(reduction[21].1 > 10.0);
+        assert!(reduction[39].1 < reduction[21].1);
+    }
+
+    #[test]
+    fn baked_ducking_keeps_the_eased_volume_curve() {
+        let music = clip(serde_json::json!({
+            "id": "m", "start": 10.0, "duration": 2.0,
+            "keyframes": { "volume": [
+                { "id": "a", "time": 0.0, "value": 0.5, "interpolation": "easeIn" },
+                { "id": "b", "time": 2.0, "value": 1.0 }
+            ] }
+        }));
+        let reduction: Vec<(f64, f64)> = (0..=4).map(|n| (10.0 + n as f64 * 0.5, 0.0)).collect();
+        let baked = bake_clip_ducking(&music, &reduction);
+
+        let values: Vec<(f64, f64)> = baked.iter().map(|k| (k.time, k.value)).collect();
+        assert_eq!(values, [(0.0, 0.5), (0.5, 0.53125), (1.0, 0.625), (1.5, 0.78125), (2.0, 1.0)]);
+    }
+
+    #[test]
+    fn baked_ducking_lowers_the_volume_and_drops_flat_points() {
+        let music = clip(serde_json::json!({ "id": "m", "start": 0.0, "duration": 2.0 }));
+        let reduction = [(0.0, 0.0), (0.5, 10.0), (1.0, 10.0), (1.5, 10.0), (2.0, 0.0), (3.0, 10.0)];
+        let baked = bake_clip_ducking(&music, &reduction);
+
+        let values: Vec<(f64, f64)> = baked.iter().map(|k| (k.time, (k.value * 1000.0).round() / 1000.0)).collect();
+        assert_eq!(values, [(0.0, 0.5), (0.5, 0.4), (1.5, 0.4), (2.0, 0.5)]);
+        assert!(baked.iter().all(|k| k.interpolation == Interpolation::Linear));
+    }
+
+    #[test]
+    fn eased_progress_matches_the_curves() {
+        let at = |interpolation: Interpolation, p: f64| eased_progress(&keyframe(0.0, 0.0, interpolation), p);
+        assert_eq!(at(Interpolation::Linear, 0.25), 0.25);
+        assert_eq!(at(Interpolation::Hold, 0.9), 0.0);
+        assert_eq!(at(Interpolation::EaseIn, 0.5), 0.25);
+        assert_eq!(at(Interpolation::EaseOut, 0.5), 0.75);
+        assert_eq!(at(Interpolation::EaseInOut, 0.5), 0.5);
+        assert!((at(Interpolation::Bezier, 0.5) - 0.5).abs() < 1e-3);
+        assert!(at(Interpolation::Bezier, 0.2) < 0.2);
+        assert_eq!(at(Interpolation::Bezier, 0.0), 0.0);
+        assert_eq!(at(Interpolation::Bezier, 1.0), 1.0);
+    }
+
+    #[test]
+    fn preview_sizes_are_even() {
+        let settings = ProjectSettings::default();
+        assert_eq!(scaled_output_size(&settings, 0.5), (960, 540));
+        assert_eq!(scaled_output_size(&settings, 0.33), (634, 356));
+        assert_eq!(scaled_output_size(&settings, 0.0001), (2, 2));
+    }
+
+    #[test]
+    fn output_size_scales_only_the_final_picture() {
+        let clips = vec![clip(serde_json::json!({}))];
+        let settings = ProjectSettings::default();
+
+        let full = build_rendering_filter(&clips, &[], 10.0, &settings, "yuv420p", None);
+        assert!(full.contains("[outv_pre]format=yuv420p[outv]"));
+
+        let preview = build_rendering_filter(&clips, &[], 10.0, &settings, "yuv420p", Some((960, 540)));
+        assert!(preview.contains("[outv_pre]scale=960:540,format=yuv420p[outv]"));
+        assert_eq!(preview.matches("[outv]").count(), 1);
+        // The layers are still composed at the project size
+        assert!(preview.contains("color=s=1920x1080"));
+    }
+
+    #[test]
+    fn preview_cache_keeps_the_newest_renders() {
+        let dir = std::env::temp_dir().join(format!("preview_cache_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let now = std::time::SystemTime::now();
+        for n in 0..5u64 {
+            let file = fs::File::create(dir.join(format!("preview_{}.mp4", n))).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(100 - n)).unwrap();
+        }
+        fs::write(dir.join("notes.txt"), b"").unwrap();
+
+        prune_preview_cache(&dir, 2);
+        let mut left: Vec<String> = fs::read_dir(&dir).unwrap().flatten().map(|e| e.file_name().to_string_lossy().into_owned()).collect();
+        left.sort();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(left, ["notes.txt", "preview_3.mp4", "preview_4.mp4"]);
+    }
+
+    #[test]
+    fn interpolation_expression_eases_between_keyframes() {
+        let expr = build_interpolation_expression(
+            &[keyframe(1.0, 0.0, Interpolation::EaseIn), keyframe(3.0, 1.0, Interpolation::Linear)],
+            "T",
+        );
+        assert_eq!(
+            expr,
+            "if(lt(T,1.000),0.000,if(lt(T,3.000),0.000+(1.000)*(((T-1.000)/2.000)*((T-1.000)/2.000)),1.000))"
+        );
+    }
+
+    #[test]
+    fn keyframe_value_follows_the_same_curves() {
+        let kfs = [keyframe(1.0, 0.0, Interpolation::EaseIn), keyframe(3.0, 1.0, Interpolation::Linear)];
+        assert_eq!(keyframe_value_at(&kfs, 0.0, 0.5), 0.0);
+        assert_eq!(keyframe_value_at(&kfs, 2.0, 0.5), 0.25);
+        assert_eq!(keyframe_value_at(&kfs, 5.0, 0.5), 1.0);
+        assert_eq!(keyframe_value_at(&[], 2.0, 0.5), 0.5);
+    }
+
+    #[test]
+    fn opacity_keyframes_update_a_colorchannelmixer() {
+        let kfs = [keyframe(0.0, 0.0, Interpolation::Linear), keyframe(0.1, 1.0, Interpolation::Linear)];
+        assert_eq!(
+            build_opacity_filter(2, &kfs, 0.0, 0.2, 20.0),
+            "format=rgba,sendcmd=c='0.0500 colorchannelmixer@opacity2 aa 0.500;0.1000 colorchannelmixer@opacity2 aa 1.000',colorchannelmixer@opacity2=aa=0.000"
+        );
+        // A flat curve needs no commands
+        assert_eq!(
+            build_opacity_filter(0, &[keyframe(0.0, 0.4, Interpolation::Linear)], 0.0, 1.0, 30.0),
+            "format=rgba,colorchannelmixer@opacity0=aa=0.400"
+        );
+    }
+
+    #[test]
+    fn keyframed_crop_moves_transparent_strips() {
+        let cropped = clip(serde_json::json!({
+            "duration": 0.1,
+            "transform": { "cropTop": 0.2 },
+            "keyframes": { "cropLeft": [
+                { "id": "a", "time": 0.0, "value": 0.0 },
+                { "id": "b", "time": 0.1, "value": 0.5 }
+            ] }
+        }));
+        let settings = ProjectSettings { fps: 10.0, ..ProjectSettings::default() };
+        assert_eq!(
+            build_crop_filter(&cropped, 1, &settings, 0.0).unwrap(),
+            "sendcmd=c='0.1000 drawbox@crop1l w iw*0.5000+1',\
+             drawbox@crop1l=x=-1:y=0:w=iw*0.0000+1:h=ih:color=black@0:t=fill:replace=1,\
+             drawbox@crop1r=x=iw*1.0000:y=0:w=iw:h=ih:color=black@0:t=fill:replace=1,\
+             drawbox@crop1t=x=0:y=-1:w=iw:h=ih*0.2000+1:color=black@0:t=fill:replace=1,\
+             drawbox@crop1b=x=0:y=ih*1.0000:w=iw:h=ih:color=black@0:t=fill:replace=1"
+        );
+    }
+
+    #[test]
+    fn keyframed_grading_updates_a_lutrgb() {
+        let graded = clip(serde_json::json!({
+            "duration": 0.1,
+            "colorCorrection": { "contrast": 1.5 },
+            "keyframes": { "exposure": [
+                { "id": "a", "time": 0.0, "value": 0.0 },
+                { "id": "b", "time": 0.1, "value": 1.0 }
+            ] }
+        }));
+        let settings = ProjectSettings { fps: 10.0, ..ProjectSettings::default() };
+        let lit = "(val*2.0000-maxval/2)*1.5000+maxval/2";
+        assert_eq!(
+            build_color_filter(&graded, 3, &settings, 0.0).unwrap(),
+            format!(
+                "sendcmd=c='0.1000 lutrgb@color3 r {l},lutrgb@color3 g {l},lutrgb@color3 b {l}',lutrgb@color3=r='{c}':g='{c}':b='{c}'",
+                l = lit, c = "(val*1.0000-maxval/2)*1.5000+maxval/2"
+            )
+        );
+    }
+
+    #[test]
+    fn eased_speed_ramps_follow_the_curve() {
+        let kfs = [keyframe(0.0, 1.0, Interpolation::EaseIn), keyframe(1.0, 2.0, Interpolation::Linear)];
+        let segments = speed_segments(&kfs);
+        assert_eq!(segments.len(), BEZIER_SAMPLES + 1);
+        // Integral of 1 + p^2 over the second, the linear ramp would give 1.5
+        let consumed = source_time_at(&segments, 1.0);
+        assert!((consumed - 4.0 / 3.0).abs() < 0.01, "{}", consumed);
+        assert!((source_time_at(&segments, 2.0) - consumed - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rotation3d_keyframes_drive_a_perspective_warp() {
+        let settings = ProjectSettings::default();
+        assert_eq!(build_transform_filter(&clip(serde_json::json!({})), 0, &settings, 0.0), None);
+
+        let rotated = clip(serde_json::json!({ "keyframes": { "rotation3d": [
+            { "id": "a", "time": 0.0, "value": { "x": 0.0, "y": 0.0, "z": 0.0 } },
+            { "id": "b", "time": 2.0, "value": { "x": 0.0, "y": 90.0, "z": 0.0 } }
+        ] } }));
+        let filter = build_transform_filter(&rotated, 0, &settings, 0.5).unwrap();
+        assert!(filter.starts_with(
+            "scale=1920:1080:force_original_aspect_ratio=decrease,format=yuva444p,pad=1924:1084:(ow-iw)/2:(oh-ih)/2:color=black@0,fps=30,perspective=x0='"
+        ));
+        assert!(filter.ends_with(":sense=destination:eval=frame,crop=1920:1080:2:2"));
+        // The Y angle follows the keyframes on the frame count, the first frame being the handle
+        assert!(filter.contains("st(1,(if(lt((in/30-0.5000),2.000),0.000+((in/30-0.5000)-0.000)*(90.000/2.000),90.000))*PI/180)"));
+        // Corners of the padded plane around the anchor: top-left first, bottom-right last
+        assert!(filter.contains("x0='st(0,"));
+        assert!(filter.contains("st(3,(-962.000)*ld(6)*cos(ld(1))+(-542.000)*ld(7)*sin(ld(0))*sin(ld(1)))"));
+        assert!(filter.contains("st(3,(962.000)*ld(6)*cos(ld(1))+(542.000)*ld(7)*sin(ld(0))*sin(ld(1)))"));
+        assert_eq!(filter.matches("1200.0/max(1,1200.0+ld(5))").count(), 8);
+    }
+
+    #[test]
+    fn transform_places_scales_and_rotates_the_clip() {
+        let placed = clip(serde_json::json!({ "transform": { "x": 100.0, "y": 50.0, "scale": 0.5, "scaleX": 2.0, "rotation": 30.0 } }));
+        let filter = build_transform_filter(&placed, 0, &ProjectSettings::default(), 0.0).unwrap();
+        assert!(filter.contains(
+            "x0='st(0,0*PI/180);st(1,0*PI/180);st(2,(0+30.0000)*PI/180);st(6,0.5000*2.0000);st(7,0.5000*1.0000);st(8,100.0000+2.0000);st(9,50.0000+2.0000);"
+        ));
+    }
+
+    #[test]
+    fn transform_keyframes_are_read_on_the_frame_time() {
+        let moving = clip(serde_json::json!({ "keyframes": { "positionX": [
+            { "id": "a", "time": 0.0, "value": 0.0 },
+            { "id": "b", "time": 1.0, "value": 960.0 }
+        ] } }));
+        let filter = build_transform_filter(&moving, 0, &ProjectSettings::default(), 0.0).unwrap();
+        assert!(filter.contains("st(8,(if(lt((in/30-0.0000),1.000),0.000+((in/30-0.0000)-0.000)*(960.000/1.000),960.000))+2.0000)"));
+        assert!(filter.contains("st(9,540.0000+2.0000)"));
+    }
+
+    #[test]
+    fn static_crop_hides_the_edges_and_keeps_the_size() {
+        let settings = ProjectSettings::default();
+        assert_eq!(build_crop_filter(&clip(serde_json::json!({ "transform": { "x": 10.0 } })), 0, &settings, 0.0), None);
+
+        let cropped = clip(serde_json::json!({ "transform": { "cropLeft": 0.1, "cropRight": 0.2 } }));
+        assert_eq!(
+            build_crop_filter(&cropped, 0, &settings, 0.0).unwrap(),
+            "crop=iw*0.7000:ih*1.0000:iw*0.1000:ih*0.0000,pad=iw/0.7000:ih/1.0000:ow*0.1000:oh*0.0000:color=black@0"
+        );
+
+        // Opposite edges that meet leave 1% of the clip
+        let closed = clip(serde_json::json!({ "transform": { "cropTop": 0.8, "cropBottom": 0.6 } }));
+        assert_eq!(
+            build_crop_filter(&closed, 0, &settings, 0.0).unwrap(),
+            "crop=iw*1.0000:ih*0.0100:iw*0.0000:ih*0.5657,pad=iw/1.0000:ih/0.0100:ow*0.0000:oh*0.5657:color=black@0"
+        );
+        // The crop goes on the fitted clip, before the plane is padded and warped
+        let filter = build_transform_filter(&cropped, 0, &settings, 0.0).unwrap();
+        assert!(filter.starts_with("scale=1920:1080:force_original_aspect_ratio=decrease,format=yuva444p,crop=iw*0.7000:"));
+    }
+
+    fn tracks(value: serde_json::Value) -> Vec<Track> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn tracks_order_layers_by_z_index_then_list_position() {
+        let list = tracks(serde_json::json!([
+            { "id": 1, "type": "video" },
+            { "id": "2", "type": "video" },
+            { "id": "3", "type": "video", "zIndex": -1 }
+        ]));
+        // The first track of the list is on top, zIndex moves a track past the others
+        assert!(track_z_order(&list, "2") < track_z_order(&list, "1"));
+        assert!(track_z_order(&list, "3") < track_z_order(&list, "2"));
+        assert!(track_z_order(&list, "9") < track_z_order(&list, "3"));
+    }
+
+    #[test]
+    fn hidden_muted_and_soloed_tracks_drop_their_outputs() {
+        let list = tracks(serde_json::json!([
+            { "id": "1", "hidden": true },
+            { "id": "2", "mute": true },
+            { "id": "3" }
+        ]));
+        assert_eq!(track_output_flags(&list, "1"), (false, true));
+        assert_eq!(track_output_flags(&list, "2"), (true, false));
+        assert_eq!(track_output_flags(&list, "3"), (true, true));
+        assert_eq!(track_output_flags(&[], "1"), (true, true));
+
+        let soloed = tracks(serde_json::json!([{ "id": "1", "solo": true }, { "id": "2" }]));
+        assert_eq!(track_output_flags(&soloed, "1"), (true, true));
+        assert_eq!(track_output_flags(&soloed, "2"), (false, false));
+        assert_eq!(track_output_flags(&soloed, "9"), (false, false));
+    }
+
+    #[test]
+    fn hidden_tracks_are_left_out_of_the_graph() {
+        let clips = [
+            clip(serde_json::json!({ "id": "a", "trackId": "1" })),
+            clip(serde_json::json!({ "id": "b", "trackId": "2" })),
+        ];
+        let list = tracks(serde_json::json!([{ "id": "1", "hidden": true }, { "id": "2" }]));
+        let graph = build_rendering_filter(&clips, &list, 10.0, &ProjectSettings::default(), "yuv420p", None);
+        assert!(!graph.contains("[0:v]"));
+        assert!(graph.contains("[1:v]"));
+        assert!(graph.contains("[0:a:0]"));
+    }
+
+    #[test]
+    fn chroma_key_is_pulled_before_the_grading() {
+        assert_eq!(build_chroma_key_filter(&clip(serde_json::json!({}))), None);
+        let disabled = clip(serde_json::json!({ "chromaKey": { "enabled": false, "color": "#00ff00" } }));
+        assert_eq!(build_chroma_key_filter(&disabled), None);
+
+        let keyed = clip(serde_json::json!({ "chromaKey": { "color": "#00ff00", "similarity": 0.3, "blend": 0.1 } }));
+        assert_eq!(build_chroma_key_filter(&keyed).unwrap(), "chromakey=color=0x00ff00:similarity=0.3000:blend=0.1000");
+
+        let graded = clip(serde_json::json!({
+            "chromaKey": { "color": "#00ff00" },
+            "colorCorrection": { "exposure": 1.0 }
+        }));
+        let graph = build_rendering_filter(&[graded], &[], 10.0, &ProjectSettings::default(), "yuv420p", None);
+        let (key, grading) = (graph.find("chromakey=").unwrap(), graph.find("colorchannelmixer=").unwrap());
+        assert!(key < grading);
+    }
+
+    #[test]
+    fn chroma_key_spill_follows_the_dominant_channel() {
+        let green = clip(serde_json::json!({ "chromaKey": { "color": "#00ff00", "spill": 0.5 } }));
+        assert_eq!(
+            build_chroma_key_filter(&green).unwrap(),
+            "chromakey=color=0x00ff00:similarity=0.1000:blend=0.0000,format=rgba,despill=type=green:mix=0.5000"
+        );
+        let blue = clip(serde_json::json!({ "chromaKey": { "color": "#1020f0", "similarity": 5.0, "spill": 2.0 } }));
+        assert_eq!(
+            build_chroma_key_filter(&blue).unwrap(),
+            "chromakey=color=0x1020f0:similarity=1.0000:blend=0.0000,format=rgba,despill=type=blue:mix=1.0000"
+        );
+    }
+
+    #[test]
+    fn blend_modes_map_to_ffmpeg_blend_options() {
+        assert_eq!(blend_mode_filter(None), None);
+        assert_eq!(blend_mode_filter(Some("normal")), None);
+        assert_eq!(blend_mode_filter(Some("Multiply")).unwrap(), "all_mode=multiply");
+        assert_eq!(blend_mode_filter(Some("hard-light")).unwrap(), "all_mode=hardlight");
+        assert_eq!(blend_mode_filter(Some("linear dodge")).unwrap(), "all_mode=addition");
+        // Modes ffmpeg defines with the layers swapped are written with the clip as B
+        assert_eq!(blend_mode_filter(Some("color-burn")).unwrap(), "all_expr='if(lte(B,0),0,max(0,255-(255-A)*255/B))'");
+    }
+
+    #[test]
+    fn blended_layers_keep_their_alpha_over_the_composite() {
+        assert_eq!(
+            build_blend_layer(2, "tmp1", "v2", 1.0, 3.0, "all_mode=screen", "tmp2"),
+            "[tmp1]split[bb2][bu2];[v2]format=gbrap,split[bt2][bm2];[bu2]format=gbrap[bg2];\
+             [bg2][bt2]blend=all_mode=screen:enable='between(t,1.0000,3.0000)'[bx2];\
+             [bm2]alphaextract[ba2];[bx2][ba2]alphamerge[bl2];\
+             [bb2][bl2]overlay=enable='between(t,1.0000,3.0000)'[tmp2]"
+        );
+
+        let clips = [
+            clip(serde_json::json!({ "id": "a", "trackId": "2" })),
+            clip(serde_json::json!({ "id": "b", "trackId": "1", "blendmode": "screen" })),
+        ];
+        let graph = build_rendering_filter(&clips, &[], 10.0, &ProjectSettings::default(), "yuv420p", None);
+        assert!(graph.contains("]blend=all_mode=screen:enable='between(t,0.0000,10.0000)'"));
+        assert_eq!(graph.matches("blend=").count(), 1);
+    }
+
+    #[test]
+    fn audio_effects_run_in_order_after_a_resample() {
+        assert_eq!(build_audio_effects(&clip(serde_json::json!({})), 48000), "");
+
+        let processed = clip(serde_json::json!({ "audioEffects": [
+            { "type": "eq", "bands": [
+                { "type": "highpass", "frequency": 80.0 },
+                { "frequency": 3000.0, "gain": -4.5, "q": 2.0 },
+                { "type": "highshelf", "frequency": 40000.0, "gain": 2.0 }
+            ] },
+            { "type": "compressor", "threshold": -20.0, "ratio": 3.0 },
+            { "type": "gate", "enabled": false },
+            { "type": "deEsser", "intensity": 0.8, "maxReduction": 2.0 },
+            { "type": "limiter" }
+        ] }));
+        assert_eq!(
+            build_audio_effects(&processed, 48000),
+            ",aresample=48000,\
+             highpass=f=80.0:t=q:w=1.000,\
+             equalizer=f=3000.0:t=q:w=2.000:g=-4.50,\
+             highshelf=f=23999.0:t=q:w=1.000:g=2.00,\
+             acompressor=threshold=0.100000:ratio=3.00:attack=20.00:release=250.00:makeup=1.0000:knee=2.8184,\
+             deesser=i=0.800:m=1.000:f=0.500,\
+             alimiter=limit=0.891251:attack=5.00:release=50.00:level=0"
+        );
+    }
+
+    #[test]
+    fn disabled_audio_effects_leave_the_clip_untouched() {
+        let bypassed = clip(serde_json::json!({ "audioEffects": [{ "type": "gate", "enabled": false }] }));
+        assert_eq!(build_audio_effects(&bypassed, 48000), "");
+        let gated = clip(serde_json::json!({ "audioEffects": [{ "type": "gate", "threshold": -40.0, "range": -24.0 }] }));
+        assert_eq!(
+            build_audio_effects(&gated, 44100),
+            ",aresample=44100,agate=threshold=0.010000:ratio=2.00:attack=20.00:release=250.00:range=0.063096"
+        );
+    }
+
+    fn export_job(id: &str, status: ExportJobStatus) -> ExportJob {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "status": status, "progress": 40, "error": "x",
+            "projectPath": "/p", "exportPath": "/p/exports/out.mp4",
+            "projectDimensions": { "width": 1920, "height": 1080 }, "clips": []
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn export_queue_keeps_the_pending_jobs_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("export_queue_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("export_queue.json");
+
+        let jobs = [
+            export_job("1", ExportJobStatus::Done),
+            export_job("2", ExportJobStatus::Running),
+            export_job("3", ExportJobStatus::Failed),
+            export_job("4", ExportJobStatus::Queued),
+            export_job("5", ExportJobStatus::Cancelled),
+        ];
+        write_export_queue(&path, &jobs).unwrap();
+        let saved = read_export_queue(&path).unwrap();
+        fs::write(&path, "{").unwrap();
+        let broken = read_export_queue(&path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let ids: Vec<&str> = saved.iter().map(|j| j.id.as_str()).collect();
+        assert_eq!(ids, ["2", "4"]);
+        // The running job starts over
+        assert!(saved.iter().all(|j| j.status == ExportJobStatus::Queued && j.progress == 0 && j.error.is_none()));
+        assert_eq!(saved[0].export_path, "/p/exports/out.mp4");
+        assert!(broken.unwrap_err().starts_with("export_queue.json inválido"));
+    }
+
+    #[test]
+    fn probe_numbers_read_fractions_and_strings() {
+        assert_eq!(probe_number(&serde_json::json!("30000/1001")), Some(30000.0 / 1001.0));
+        assert_eq!(probe_number(&serde_json::json!("0/0")), None);
+        assert_eq!(probe_number(&serde_json::json!("12.5")), Some(12.5));
+        assert_eq!(probe_number(&serde_json::json!(48000)), Some(48000.0));
+        assert_eq!(probe_number(&serde_json::json!("N/A")), None);
+        assert_eq!(probe_number(&serde_json::Value::Null), None);
+    }
+
+    #[test]
+    fn ffprobe_streams_become_stream_info() {
+        let video = parse_stream_info(&serde_json::json!({
+            "index": 0, "codec_type": "video", "codec_name": "h264", "profile": "High",
+            "width": 1920, "height": 1080, "avg_frame_rate": "24000/1001", "r_frame_rate": "24/1",
+            "nb_frames": "1438", "pix_fmt": "yuv420p", "bits_per_raw_sample": "8", "duration": "60.0",
+            "side_data_list": [{ "side_data_type": "Display Matrix", "rotation": -90 }]
+        }));
+        assert_eq!((video.index, video.kind.as_str(), video.codec.as_str()), (0, "video", "h264"));
+        assert_eq!((video.width, video.height), (Some(1920), Some(1080)));
+        assert_eq!(video.frame_rate, Some(24000.0 / 1001.0));
+        assert_eq!(video.real_frame_rate, Some(24.0));
+        assert_eq!(video.frame_count, Some(1438));
+        assert_eq!(video.rotation, Some(-90.0));
+        assert_eq!(video.bit_depth, Some(8));
+        assert_eq!(video.duration, Some(60.0));
+
+        let audio = parse_stream_info(&serde_json::json!({
+            "index": 1, "codec_type": "audio", "codec_name": "aac", "profile": "unknown",
+            "channels": 6, "channel_layout": "5.1", "sample_rate": "48000", "bits_per_sample": 0,
+            "tags": { "language": "por" }
+        }));
+        assert_eq!((audio.kind.as_str(), audio.channels, audio.sample_rate), ("audio", Some(6), Some(48000)));
+        assert_eq!(audio.channel_layout.as_deref(), Some("5.1"));
+        assert_eq!(audio.language.as_deref(), Some("por"));
+        assert_eq!(audio.profile, None);
+        assert_eq!(audio.bit_depth, None);
+        assert_eq!(audio.rotation, None);
+
+        // The old rotate tag is clockwise, the display matrix counter-clockwise
+        let tagged = parse_stream_info(&serde_json::json!({ "codec_type": "video", "tags": { "rotate": "90" } }));
+        assert_eq!(tagged.rotation, Some(-90.0));
+
+        let data = parse_stream_info(&serde_json::json!({}));
+        assert_eq!((data.kind.as_str(), data.codec.as_str()), ("data", ""));
+    }
+
+    #[test]
+    fn proxies_live_next_to_the_project_videos() {
+        assert_eq!(
+            proxy_path(Path::new("/p/videos/a.mov")).unwrap(),
+            Path::new("/p/proxies/a.mov.mp4")
+        );
+        assert_eq!(proxy_path(Path::new("/p/audio/a.wav")), None);
+    }
+
+    #[test]
+    fn proxies_are_used_only_while_newer_than_their_source() {
+        let dir = std::env::temp_dir().join(format!("proxies_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("videos")).unwrap();
+        fs::create_dir_all(dir.join("proxies")).unwrap();
+        let source = dir.join("videos").join("a.mov");
+        let proxy = dir.join("proxies").join("a.mov.mp4");
+        let now = std::time::SystemTime::now();
+        fs::File::create(&source).unwrap().set_modified(now - std::time::Duration::from_secs(10)).unwrap();
+
+        let missing = ready_proxy_for(&source);
+        fs::File::create(&proxy).unwrap().set_modified(now).unwrap();
+        let ready = ready_proxy_for(&source);
+        let original = original_for_proxy(&proxy);
+        fs::File::create(&source).unwrap().set_modified(now + std::time::Duration::from_secs(10)).unwrap();
+        let stale = ready_proxy_for(&source);
+        let orphan = original_for_proxy(&dir.join("proxies").join("b.mov.mp4"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(missing, None);
+        assert_eq!(ready, Some(proxy));
+        assert_eq!(original, Some(source));
+        assert_eq!(stale, None);
+        assert_eq!(orphan, None);
+    }
+
+    #[test]
+    fn proxy_args_keep_the_timestamps_and_scale_down() {
+        let args = build_proxy_args(Path::new("/p/videos/a.mov"), Path::new("/p/proxies/a.mov.part"), 540);
+        let value = |flag: &str| args.iter().position(|a| a == flag).map(|i| args[i + 1].as_str());
+        assert_eq!(value("-i"), Some("/p/videos/a.mov"));
+        assert_eq!(value("-vf"), Some("scale=-2:540"));
+        assert_eq!(value("-fps_mode"), Some("passthrough"));
+        assert_eq!(value("-f"), Some("mp4"));
+        assert_eq!(args.last().unwrap(), "/p/proxies/a.mov.part");
+    }
+}
@@ -68,13 +68,14 @@ import {
 
 import Waveform from "@/components/Waveform";
 import ProjectSettingsModal from "@/components/ProjectSettingsModal";
+import ExportSettingsModal, { ExportOptions } from "@/components/ExportSettingsModal";
 import { PropertiesAside } from '@/components/PropertiesAside';
 import { ItensAside } from './components/ItensAside';
 
 
 
 import { invoke } from '@tauri-apps/api/core';
-import { open, save } from '@tauri-apps/plugin-dialog';
+import { open, save, ask } from '@tauri-apps/plugin-dialog';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { aside, track } from 'framer-motion/client';
 import { convertFileSrc } from '@tauri-apps/api/core';
@@ -118,9 +119,25 @@ interface Rotation
   rot3d: number;
 }
 
+// Style of a text clip, same names as TextStyle in Rust
+interface TextStyle {
+  content: string;
+  fontSize?: number;
+  color?: string;
+  outlineColor?: string;
+  outlineWidth?: number;
+  boxColor?: string;
+  align?: 'left' | 'center' | 'right';
+  verticalAlign?: 'top' | 'middle' | 'bottom';
+  margin?: number;
+}
+
 interface Clip {
   id: string;
   name: string;
+  type?: 'text'; // only text clips have it, the others are typed by the file name
+  path?: string;
+  text?: TextStyle;
   start: number; // begin of the clip in relation the timeline
   duration: number;
   color: string;
@@ -130,6 +147,7 @@ interface Clip {
   originalduration: number;
   blendmode?: 'normal' | 'overlay' | 'screen' | 'multiply' | 'lineardodge' | null;
   mute?: boolean;
+  loop?: boolean; // GIF/WebP: repeat (default) or hold the last frame
   fadein?: number;
   fadeout?: number;
   fadeinAudio?: number;
@@ -171,12 +189,36 @@ interface Asset {
   dimentions?: Position
 }
 
+// assets.json entry returned by import_asset / conform_asset
+interface AssetRecord {
+  name: string;
+  source: string;
+  original: string;
+  conformed?: string | null;
+  variableFrameRate: boolean;
+  vfrRatio: number;
+  frameRate?: number | null;
+  warning?: string | null;
+}
+
+// Numbered frames found by detect_image_sequences
+interface ImageSequence {
+  name: string;
+  directory: string;
+  pattern: string;
+  startNumber: number;
+  frameCount: number;
+  frameRate: number;
+}
+
 interface Tracks
 {
   id: number;
   type:  'audio' | 'video' | 'effects';
   lock?: boolean;
   mute?: boolean;
+  role?: 'dialogue' | 'music' | 'effects';
+  ducking?: { enabled?: boolean };
 
 }
 
@@ -418,7 +460,7 @@ const handleSaveSettings = async (newSettings: ProjectSettings) => {
 
   const imageExtensions = ['jpg', 'jpeg', 'png', 'webp'];
   const audioExtensions = ['mp3', 'wav', 'ogg'];
-  const videoExtensions = ['mp4', 'mkv', 'avi', 'mov'];
+  const videoExtensions = ['mp4', 'mkv', 'avi', 'mov', 'imgseq', 'gif'];
 
   
     // Default zoom: 100 pixels represents 1 second
@@ -555,6 +597,8 @@ const [topAudios, setTopAudios] = useState<Clip [] | null>(null);
 
 //State management for rendering feedback
 const [renderStatus, setRenderStatus] = useState<'idle' | 'rendering' | 'success'>('idle');
+const [isExportOpen, setIsExportOpen] = useState(false);
+const exportJobIdRef = useRef<string | null>(null); // job da fila acompanhado pela barra de progresso
 const [renderPercent, setRenderPercent] = useState(0);
 
 
@@ -568,16 +612,44 @@ const [isPlaying2, setIsPlaying2] = useState(false);
 
 // Dentro do seu componente App
 useEffect(() => {
-  const unlisten = listen<number>('export-progress', (event) => {
-    // Update the state you're using in the progress bar
-    console.log("Progresso recebido:", event.payload);
-    setRenderPercent(event.payload);
-    
-      
+  // Cada job da fila manda o próprio progresso, só acompanha o que foi iniciado aqui
+  const unlisten = listen<{ id: string; status: string; progress: number; error?: string | null }>('export-job', (event) => {
+    const job = event.payload;
+    if (job.id !== exportJobIdRef.current) return;
+
+    if (job.status === 'running' || job.status === 'done') {
+      setRenderPercent(job.progress);
+    } else if (job.status === 'failed' || job.status === 'cancelled') {
+      exportJobIdRef.current = null;
+      setRenderStatus('idle');
+      setRenderPercent(0);
+    }
+  });
+
+  const unlistenLoudness = listen<{ input_i: string; input_tp: string }>('export-loudness', (event) => {
+    console.log("Loudness medido:", event.payload);
+    showNotify(`Measured ${event.payload.input_i} LUFS / ${event.payload.input_tp} dBTP, normalizing`, "success");
+  });
+
+  // The export runs after export_video returns, its failures only arrive here
+  const unlistenError = listen<string>('export-error', (event) => {
+    console.error("Export Error:", event.payload);
+    setRenderStatus('idle');
+    setRenderPercent(0);
+    showNotify(event.payload || "Export failed", "error");
+  });
+
+  // The queue could not be saved in the project, the running exports are not affected
+  const unlistenQueueError = listen<string>('export-queue-error', (event) => {
+    console.error("Export Queue Error:", event.payload);
+    showNotify(event.payload, "error");
   });
 
   return () => {
     unlisten.then(f => f());
+    unlistenLoudness.then(f => f());
+    unlistenError.then(f => f());
+    unlistenQueueError.then(f => f());
   };
 }, []);
 
@@ -724,7 +796,8 @@ const separateAudio = async (clip: Clip) =>
 const handleCancelExport = async () => {
   try {
     // [English Comment] Signal Rust to kill the FFmpeg task
-    await invoke('cancel_export');
+    await invoke('cancel_export', { jobId: exportJobIdRef.current });
+    exportJobIdRef.current = null;
     setRenderStatus('idle');
     setRenderPercent(0);
     console.log("Export cancelled by user");
@@ -735,30 +808,32 @@ const handleCancelExport = async () => {
 
 
 // Dentro da sua função de exportação no App.tsx
-const startExport = async () => {
+const startExport = async (options: ExportOptions) => {
   try {
-    // 1. Ativa a UI de renderização e zera o progresso
-    setRenderPercent(0);
-    setRenderStatus('rendering'); 
-
+    setIsExportOpen(false);
 
     if(!currentProjectPath)
      return
 
   const safeName = currentProjectPath.replace(/[^a-z0-0]/gi, '_').toLowerCase();
+  const extension = options.preset?.container ?? 'mp4';
 
   const targetPath = await save({
     title: 'Export Final Video',
     filters: [{
       name: 'Video',
-      extensions: ['mp4']
+      extensions: [extension]
     }],
-    defaultPath: `${safeName}.mp4`
+    defaultPath: `${safeName}.${extension}`
   });
 
   // If the user cancels the dialog, targetPath will be null
   if (!targetPath) return;
 
+    // 1. Ativa a UI de renderização e zera o progresso
+    setRenderPercent(0);
+    setRenderStatus('rendering'); 
+
 
   const sanitizeNumber = (num: number): number => {
   return Math.round(num * 100) / 100;
@@ -767,10 +842,12 @@ const startExport = async () => {
   const clips_format = clips.map( c => { 
     
     
+    const isText = c.type === 'text';
+
     return {
-    ...c ,path: `${currentProjectPath}/videos/${c.name}` ,
+    ...c ,path: isText ? (c.path ?? '') : `${currentProjectPath}/videos/${c.name}` ,
     trackId: c.trackId.toString(),
-     type: knowTypeByAssetName(c.name),
+     type: isText ? 'text' : knowTypeByAssetName(c.name),
       mute: c.mute ?? false,
       beginmoment: sanitizeNumber(c.beginmoment),
   duration: sanitizeNumber(c.duration),
@@ -781,12 +858,16 @@ const startExport = async () => {
 
 
 
-    // 2. Chama o Rust
-    await invoke('export_video', {
+    // 2. Chama o Rust, que devolve o id do job na fila
+    exportJobIdRef.current = await invoke<string>('export_video', {
       projectPath: currentProjectPath,
       exportPath: targetPath, 
       projectDimensions: { width: projectConfig.width || 1980, height: projectConfig.height || 1080 },
-      clips: clips_format
+      clips: clips_format,
+      tracks: tracks,
+      loudness: options.loudness,
+      preset: options.preset,
+      range: options.range
     });
 
     // Se chegar aqui, terminou com sucesso
@@ -797,6 +878,7 @@ const startExport = async () => {
   } catch (error) {
     console.error("Export Error:", error);
     setRenderStatus('idle'); // Fecha se der erro
+    showNotify(String(error), "error");
   }
 };
 
@@ -814,7 +896,7 @@ const updatePreview = async (currentTime: number) => {
   const currentClips = clips.filter(clip => 
     currentTime >= clip.start  && 
     currentTime <= (clip.start  + clip.duration) && 
-    knowTypeByAssetName(clip.name, true) === 'video'
+    (clip.type === 'text' || knowTypeByAssetName(clip.name, true) === 'video')
   );
 
   if (currentClips.length == 0)
@@ -889,6 +971,7 @@ const updateAudio = () => {
   const currentClips = clips.filter(clip => 
     currentTime >= clip.start  && 
     currentTime <= (clip.start  + clip.duration) && 
+    clip.type !== 'text' &&
     knowTypeByAssetName(clip.name) !== 'image' &&
     (tracks.find(t => t.id === clip.trackId)?.mute === false ||
     !(tracks.find(t => t.id === clip.trackId)?.mute)) &&
@@ -1496,6 +1579,55 @@ useEffect(() => {
 }, [canvasRef.current, projectConfig.width, projectConfig.height]); // Adicionado canvasRef.current aqui
 
 
+// Text clips have no file, their frame is drawn here the size of the project (as the export does)
+const renderTextFrame = (clip: Clip) => {
+  const canvas = document.createElement('canvas');
+  canvas.width = projectConfig.width;
+  canvas.height = projectConfig.height;
+  const ctx = canvas.getContext('2d');
+  const style = clip.text;
+
+  if (ctx && style) {
+    const fontSize = style.fontSize ?? canvas.height / 12;
+    const margin = style.margin ?? 0;
+    const lines = style.content.replace(/\s+$/, '').split('\n');
+    const align = style.align ?? 'center';
+
+    ctx.font = `${fontSize}px sans-serif`;
+    ctx.textBaseline = 'top';
+    ctx.textAlign = align;
+
+    const x = align === 'left' ? margin : align === 'right' ? canvas.width - margin : canvas.width / 2;
+    const blockHeight = lines.length * fontSize;
+    const top = style.verticalAlign === 'top' ? margin
+      : style.verticalAlign === 'bottom' ? canvas.height - blockHeight - margin
+      : (canvas.height - blockHeight) / 2;
+
+    lines.forEach((line, i) => {
+      const y = top + i * fontSize;
+      if (style.boxColor) {
+        const w = ctx.measureText(line).width;
+        const left = align === 'left' ? x : align === 'right' ? x - w : x - w / 2;
+        ctx.fillStyle = style.boxColor;
+        ctx.fillRect(left, y, w, fontSize);
+      }
+      if (style.outlineWidth) {
+        ctx.lineWidth = style.outlineWidth * 2;
+        ctx.strokeStyle = style.outlineColor ?? 'black';
+        ctx.strokeText(line, x, y);
+      }
+      ctx.fillStyle = style.color ?? 'white';
+      ctx.fillText(line, x, y);
+    });
+  }
+
+  return new Promise<{id: string, img: HTMLImageElement, clip: any}>((resolve) => {
+    const img = new Image();
+    img.onload = () => resolve({ id: clip.id, img, clip });
+    img.src = canvas.toDataURL();
+  });
+};
+
 const fetchFramesFromRust = async (time: number) => {
   // Garante que topClips existe
   if (!topClips.current) return [];
@@ -1505,6 +1637,8 @@ const fetchFramesFromRust = async (time: number) => {
 
 
   const framePromises = activeClips.map(async (clip) => {
+    if (clip.type === 'text') return renderTextFrame(clip);
+
     const timelineRelativeTime = time - clip.start;
     const clipTimeMs = (timelineRelativeTime + (clip.beginmoment || 0)) * 1000;
     const path = `${currentProjectPath}/videos/${clip.name}`;
@@ -2289,7 +2423,7 @@ const handlePaste = () => {
 
     // 5. Ensure the target track exists
     if (!updatedTracks.some(t => t.id === targetTrack)) {
-      const clipType = knowTypeByAssetName(pastedClip.name, true);
+      const clipType = pastedClip.type === 'text' ? 'video' : knowTypeByAssetName(pastedClip.name, true);
       updatedTracks.push({
         id: targetTrack,
         type: clipType as 'video' | 'audio' | 'effects'
@@ -2560,7 +2694,7 @@ const handleResize = (id: string, deltaX: number, side: 'left' | 'right') => {
     if (clip.id !== id) return clip;
 
     const asset = assets.find(a => a.name === clip.name);
-    const isImage = asset?.type === 'image';
+    const isImage = asset?.type === 'image' || clip.type === 'text';
 
     const noKeyframesSpeed = (!clip.keyframes?.speed) || (clip.keyframes?.speed?.length == 0)
 
@@ -2803,7 +2937,7 @@ const handleResize = (id: string, deltaX: number, side: 'left' | 'right') => {
 
       if ((e.ctrlKey || e.metaKey) && e.key === 'Enter') {
       e.preventDefault();
-      startExport();
+      setIsExportOpen(true);
     }
 
 
@@ -3324,7 +3458,7 @@ const createClipOnNewTrack =  async (assetName: string, dropTime: number, beginm
   
   try
   {
-    meta = await invoke<{duration: number}>('get_duration', { path: path });
+    meta = await invoke<{duration: number}>('get_duration', { path: path, projectPath: currentProjectPath });
     
   }
   catch (err)
@@ -3403,6 +3537,32 @@ const createClipOnNewTrack =  async (assetName: string, dropTime: number, beginm
 
 }
 
+// Text clips have no asset, they go on a new video track at the playhead
+const addTextClip = () => {
+  const newTrackId = tracks.length > 0 ? Math.max(...tracks.map(t => t.id)) + 1 : 0;
+
+  const newClip: Clip = {
+    id: crypto.randomUUID(),
+    name: 'Text',
+    type: 'text',
+    path: '',
+    text: { content: 'Title', color: '#ffffff', align: 'center', verticalAlign: 'middle' },
+    start: currentTimeRef.current,
+    duration: 5,
+    originalduration: 5,
+    color: getRandomColor(),
+    trackId: newTrackId,
+    maxduration: 5,
+    beginmoment: 0,
+    dimentions: { x: projectConfig.width, y: projectConfig.height },
+    scale: 1
+  };
+
+  setTracks(prev => [...prev, { id: newTrackId, type: 'video' }]);
+  setClips(prev => [...prev, newClip]);
+  setSelectedClipIds([newClip.id]);
+};
+
 //create new timelines dropping assets close of a track
 const handleDropOnEmptyArea = (e: React.DragEvent) => {
   e.preventDefault();
@@ -3540,9 +3700,13 @@ const handleNativeDrop = async (paths: string[], mouseX: number, mouseY: number)
   //console.log(`Mouse X: ${mouseX}, Rect Left: ${rect.left}, Scroll: ${scrollLeft}, Final Time: ${dropTime}`);
   
   if (isOutsideTimeline) {
+    // Frames of the same sequence are asked about (and imported) only once
+    const sequenceChoices = new Map<string, boolean>();
     for (const path of paths) {
       try {
 
+        if (await importSequenceIfAny(path, sequenceChoices)) continue;
+
         const fileName = path.split(/[\\/]/).pop() || "File";
         const extension = fileName.split('.').pop()?.toLowerCase() || '';
 
@@ -3559,7 +3723,7 @@ const handleNativeDrop = async (paths: string[], mouseX: number, mouseY: number)
 
 
         
-        await invoke('import_asset', { projectPath: currentProjectPath, filePath: path });
+        await importAsset(path);
       } catch (err) {
         console.error("Import error:", err);
       }
@@ -3589,7 +3753,7 @@ const handleNativeDrop = async (paths: string[], mouseX: number, mouseY: number)
 
 
       try {
-        await invoke('import_asset', { projectPath: currentProjectPath, filePath: path });
+        await importAsset(path);
         const fileName = path.split(/[\\/]/).pop() || "Asset";
 
         var meta
@@ -3597,7 +3761,7 @@ const handleNativeDrop = async (paths: string[], mouseX: number, mouseY: number)
         
         try
         {
-          meta = await invoke<{duration: number}>('get_duration', { path: path });
+          meta = await invoke<{duration: number}>('get_duration', { path: path, projectPath: currentProjectPath });
           
         }
         catch (err)
@@ -3610,7 +3774,7 @@ const handleNativeDrop = async (paths: string[], mouseX: number, mouseY: number)
         {
             try
             {
-              dimentions = await invoke< Position >('get_asset_dimensions', { path: path });
+              dimentions = await invoke< Position >('get_asset_dimensions', { path: path, projectPath: currentProjectPath });
               
             }
             catch (err)
@@ -3804,7 +3968,7 @@ const handleFadeDrag = (e: React.MouseEvent, clipId: string, type: 'in' | 'out',
 
       if (type !== 'image') {
         try {
-          const meta = await invoke<{duration: number}>('get_duration', { path: filePath });
+          const meta = await invoke<{duration: number}>('get_duration', { path: filePath, projectPath: currentProjectPath });
           duration = meta.duration;
         } catch (err) {
           console.warn(`Não foi possível ler meta de ${filename}`, err);
@@ -3823,6 +3987,12 @@ const handleFadeDrag = (e: React.MouseEvent, clipId: string, type: 'in' | 'out',
           } catch (e) {
             console.error("Falha na extração automática:", e);
           }
+
+          // Proxy em segundo plano; o progresso chega pelo evento 'proxy-job'
+          invoke('generate_proxies', {
+            projectPath: currentProjectPath,
+            fileNames: [filename]
+          }).catch((e) => console.error("Falha ao gerar proxy:", e));
       }
 
 
@@ -3830,7 +4000,8 @@ const handleFadeDrag = (e: React.MouseEvent, clipId: string, type: 'in' | 'out',
       {
            try {
             dimentions =  await invoke('get_asset_dimensions', { 
-              path: filePath
+              path: filePath,
+              projectPath: currentProjectPath
             });
 
 
@@ -4255,7 +4426,7 @@ const handleDropOnTimeline = (e: React.DragEvent, trackId: number) => {
       const trackChoose = tracks.find( t => t.id === targetTrack)
 
       //If there is a collision, we move the vehicle to a track above the existing ones.
-      if (isSpaceOccupied(targetTrack, targetStart, clip.duration, clip.id) || trackChoose?.type !==  knowTypeByAssetName(clip.name,true)) {
+      if (isSpaceOccupied(targetTrack, targetStart, clip.duration, clip.id) || trackChoose?.type !== (clip.type === 'text' ? 'video' : knowTypeByAssetName(clip.name,true))) {
         maxTrackId++;
         targetTrack = maxTrackId;
       }
@@ -4345,6 +4516,59 @@ const filteredAssets = assets.filter(asset =>
 
 
 
+// Copia o arquivo para o projeto; vídeos com frame rate variável podem ser convertidos
+// para constante (o original fica em originals/)
+const importAsset = async (path: string) => {
+  let record = await invoke<AssetRecord>('import_asset', { projectPath: currentProjectPath, filePath: path, conform: false });
+
+  if (record.warning) {
+    showNotify(record.warning, "error");
+  }
+
+  if (record.variableFrameRate && !record.conformed) {
+    const conform = await ask(
+      `${record.name} has a variable frame rate, which makes cuts drift against the audio. Convert it to a constant ${projectConfig.fps || 30} fps? The original is kept.`,
+      { title: 'Variable frame rate', kind: 'warning' }
+    );
+    if (conform) {
+      try {
+        record = await invoke<AssetRecord>('conform_asset', { projectPath: currentProjectPath, fileName: record.name });
+      } catch (err) {
+        console.error("Conform error:", err);
+        showNotify("Could not convert to constant frame rate", "error");
+      }
+    }
+  }
+  return record;
+};
+
+// Uma pasta de frames, ou um frame numerado (shot_0001.exr), pode entrar como um único
+// asset de sequência. `choices` guarda a resposta por sequência para não perguntar de novo.
+// Returns true when the path was handled as (part of) a sequence.
+const importSequenceIfAny = async (path: string, choices: Map<string, boolean> = new Map()) => {
+  let sequences: ImageSequence[];
+  try {
+    sequences = await invoke<ImageSequence[]>('detect_image_sequences', { path, frameRate: projectConfig.fps });
+  } catch {
+    return false; // Not a numbered frame
+  }
+  const sequence = sequences[0];
+  if (!sequence) return false;
+
+  const key = `${sequence.directory}/${sequence.pattern}`;
+  if (choices.has(key)) return choices.get(key)!;
+
+  const asSequence = await ask(
+    `Found an image sequence of ${sequence.frameCount} frames (${sequence.pattern}). Import it as a single clip?`,
+    { title: 'Image sequence', kind: 'info' }
+  );
+  choices.set(key, asSequence);
+  if (!asSequence) return false;
+
+  await invoke<string>('import_image_sequence', { projectPath: currentProjectPath, path, frameRate: projectConfig.fps });
+  return true;
+};
+
 const handleImportFile = async () => {
   try {
     // 1. Open native dialog to select a file
@@ -4352,13 +4576,20 @@ const handleImportFile = async () => {
       multiple: false,
       filters: [{
         name: 'Media',
-        extensions: ['mp4', 'mkv', 'avi', 'mov', 'mp3', 'wav', 'ogg', 'png', 'jpg', 'jpeg', 'webp']
+        extensions: ['mp4', 'mkv', 'avi', 'mov', 'gif', 'mp3', 'wav', 'ogg', 'png', 'jpg', 'jpeg', 'webp', 'exr', 'tif', 'tiff']
       }]
     });
 
     if (!selected || Array.isArray(selected)) return; 
     
     const filePath = selected as string;
+
+    if (await importSequenceIfAny(filePath)) {
+      loadAssets();
+      showNotify("Image sequence imported", "success");
+      return;
+    }
+
     const fileName = filePath.split(/[\\/]/).pop() || "File";
     const extension = fileName.split('.').pop()?.toLowerCase() || '';
 
@@ -4375,7 +4606,7 @@ const handleImportFile = async () => {
       return;
     }
 
-    await invoke('import_asset', { projectPath: currentProjectPath, filePath: filePath });
+    await importAsset(filePath);
      loadAssets();
     showNotify("Assets imported", "success");
 
@@ -5457,9 +5688,10 @@ return (
             >
               <Youtube size={14} /> Download
             </button>
+            <button className="p-2 hover:bg-zinc-800 rounded-full text-zinc-400" title='Add text' onClick={addTextClip}><Type size={16}/></button>
             <button className="p-2 hover:bg-zinc-800 rounded-full text-zinc-400" title='Post in social media'><Share2 size={16}/></button>
             <button className="p-2 hover:bg-zinc-800 rounded-full text-zinc-400" title='Settings' onClick={() => setIsSettingsOpen(true)}><Settings size={16}/></button>
-            <button className="p-2 hover:bg-zinc-800 rounded-full text-zinc-400" title='Export video' onClick={()=> { startExport();}}><Import size={16}/></button>
+            <button className="p-2 hover:bg-zinc-800 rounded-full text-zinc-400" title='Export video' onClick={()=> { setIsExportOpen(true);}}><Import size={16}/></button>
           </div>
         </header>
 
@@ -6057,7 +6289,8 @@ return (
             const cacheKey = `${clip.id}-${clip.beginmoment}-${clip.duration}`;
             const thumbs = timelineThumbs[cacheKey];
             const assetTarget = assets.find( a => a.name === clip.name) || null
-            if(!assetTarget) return
+            const isText = clip.type === 'text'
+            if(!assetTarget && !isText) return
             const isVideo = (assetTarget?.type  === 'video')
             
             let margintitle = pixelsPerSecond > 30 ? -15 : -15
@@ -6065,7 +6298,7 @@ return (
 
 
             margintitle = pixelsPerSecond > 50 ? 30 : margintitle
-            const isAudioOnly = assetTarget.type === 'audio';
+            const isAudioOnly = assetTarget?.type === 'audio';
             const currentFadeIn = isAudioOnly ? (clip.fadeinAudio || 0) : (clip.fadein || 0);
             const currentFadeOut = isAudioOnly ? (clip.fadeoutAudio || 0) : (clip.fadeout || 0);
 
@@ -6077,7 +6310,7 @@ return (
               <motion.div 
               key={clip.id} layoutId={clip.id}
               draggable="true"
-              onContextMenu={(e) => handleContextMenu(e, assetTarget?.type, clip)}
+              onContextMenu={(e) => handleContextMenu(e, isText ? 'text' : assetTarget?.type, clip)}
               onDragStart={(e) => handleDragStart(e, clip.color, track.id, clip.duration, clip.name, true, clip.id)}
               onClick={(e) => { e.stopPropagation(); toggleClipSelection(clip.id, e.shiftKey || e.ctrlKey); setContextMenu(null); 
                 
@@ -6432,9 +6665,9 @@ return (
               <div className="relative flex items-center justify-start w-full h-full px-4 overflow-hidden pointer-events-none">
                 <p 
                   className="text-[9px] font-black text-white uppercase italic leading-none drop-shadow-lg truncate max-w-[80%]"
-                  style={{ marginLeft: assetTarget?.type === 'audio' ? '0' : '64px' }} // Ajusta se houver thumbnail
+                  style={{ marginLeft: assetTarget?.type === 'audio' || isText ? '0' : '64px' }} // Ajusta se houver thumbnail
                 >
-                  {clip.name}
+                  {isText ? clip.text?.content : clip.name}
                 </p>
               </div>
 
@@ -6697,6 +6930,15 @@ return (
     currentSettings={projectConfig}
     onSave={handleSaveSettings}
   />
+
+  <ExportSettingsModal
+    isOpen={isExportOpen}
+    onClose={() => setIsExportOpen(false)}
+    tracks={tracks}
+    onTracksChange={setTracks}
+    timelineDuration={clips.reduce((end, c) => Math.max(end, c.start + c.duration), 0)}
+    onExport={startExport}
+  />
   </div>
 );
 }
\ No newline at end of file
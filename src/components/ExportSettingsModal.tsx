@@ -0,0 +1,260 @@
+import React, { useState, useEffect } from 'react';
+import { X, Film, Volume2, Scissors, Layers } from 'lucide-react';
+import { invoke } from '@tauri-apps/api/core';
+
+
+// Mesmos nomes (camelCase) que o ExportPreset do Rust
+export interface ExportPreset {
+  name: string;
+  container: string;
+  videoCodec: string;
+  rateControl?: string | null;
+  crf?: number | null;
+  videoBitrate?: number | null;
+  speed?: string | null;
+  profile?: string | null;
+  pixelFormat?: string | null;
+  audioCodec: string;
+  audioBitrate?: number | null;
+}
+
+export interface LoudnessTarget {
+  integrated: number;
+  truePeak: number;
+  lra: number;
+}
+
+export interface ExportTrack {
+  id: number;
+  type: 'audio' | 'video' | 'effects';
+  lock?: boolean;
+  mute?: boolean;
+  role?: 'dialogue' | 'music' | 'effects';
+  ducking?: { enabled?: boolean };
+}
+
+export interface ExportOptions {
+  preset: ExportPreset | null;
+  loudness: LoudnessTarget | null;
+  range: { in: number; out: number } | null;
+}
+
+interface Props {
+  isOpen: boolean;
+  onClose: () => void;
+  tracks: ExportTrack[];
+  onTracksChange: (tracks: ExportTrack[]) => void;
+  timelineDuration: number;
+  onExport: (options: ExportOptions) => void;
+}
+
+const ExportSettingsModal: React.FC<Props> = ({ isOpen, onClose, tracks, onTracksChange, timelineDuration, onExport }) => {
+  const [presets, setPresets] = useState<ExportPreset[]>([]);
+  const [presetName, setPresetName] = useState<string>('');
+  const [normalize, setNormalize] = useState(false);
+  const [loudness, setLoudness] = useState<LoudnessTarget>({ integrated: -14, truePeak: -1, lra: 11 });
+  const [useRange, setUseRange] = useState(false);
+  const [range, setRange] = useState({ in: 0, out: timelineDuration });
+
+  useEffect(() => {
+    if (!isOpen) return;
+
+    invoke<ExportPreset[]>('list_export_presets')
+      .then(list => {
+        setPresets(list);
+        if (!list.some(p => p.name === presetName)) setPresetName(list[0]?.name ?? '');
+      })
+      .catch(err => console.error("Failed to load export presets:", err));
+
+    setRange(r => ({ in: Math.min(r.in, timelineDuration), out: r.out > 0 ? Math.min(r.out, timelineDuration) : timelineDuration }));
+  }, [isOpen]);
+
+  if (!isOpen) return null;
+
+  const audioTracks = tracks.filter(t => t.type === 'audio');
+
+  const updateTrack = (id: number, changes: Partial<ExportTrack>) => {
+    onTracksChange(tracks.map(t => t.id === id ? { ...t, ...changes } : t));
+  };
+
+  const validRange = !useRange || (range.out > range.in && range.in >= 0);
+
+  const handleExport = () => {
+    onExport({
+      preset: presets.find(p => p.name === presetName) ?? null,
+      loudness: normalize ? loudness : null,
+      range: useRange ? range : null,
+    });
+  };
+
+  return (
+    <div className="fixed inset-0 z-[100] flex items-center justify-center bg-black/60 backdrop-blur-sm">
+      <div className="bg-[#0f0f0f] border border-white/10 w-full max-w-lg rounded-lg shadow-2xl overflow-hidden animate-in fade-in zoom-in duration-200">
+
+        {/* Modal Header */}
+        <div className="flex items-center justify-between px-6 py-4 border-b border-white/5">
+          <div className="flex items-center gap-2">
+            <Layers size={18} className="text-blue-400" />
+            <h2 className="text-sm font-semibold text-white tracking-wide">Export</h2>
+          </div>
+          <button
+            onClick={onClose}
+            className="text-zinc-500 hover:text-white transition-colors"
+          >
+            <X size={20} />
+          </button>
+        </div>
+
+        {/* Modal Body */}
+        <div className="p-6 space-y-6 max-h-[70vh] overflow-y-auto custom-scrollbar text-[12px]">
+
+          {/* Section: Preset */}
+          <div className="space-y-3">
+            <label className="text-zinc-400 font-medium flex items-center gap-2">
+              <Film size={14} /> Preset
+            </label>
+            <select
+              className="w-full bg-white/5 border border-white/10 rounded px-2 py-1.5 text-white outline-none appearance-none"
+              value={presetName}
+              onChange={(e) => setPresetName(e.target.value)}
+            >
+              {presets.map(p => (
+                <option key={p.name} value={p.name} className="bg-[#0f0f0f]">
+                  {p.name} ({p.container} · {p.videoCodec} · {p.audioCodec})
+                </option>
+              ))}
+            </select>
+          </div>
+
+          {/* Section: Range */}
+          <div className="space-y-3">
+            <label className="text-zinc-400 font-medium flex items-center gap-2">
+              <Scissors size={14} /> Range
+            </label>
+            <label className="flex items-center gap-2 text-zinc-300">
+              <input type="checkbox" checked={useRange} onChange={(e) => setUseRange(e.target.checked)} />
+              Export only part of the timeline
+            </label>
+            {useRange && (
+              <div className="flex gap-4 items-end">
+                <div className="flex-1 space-y-2">
+                  <span className="text-zinc-500 text-[10px]">In (s)</span>
+                  <input
+                    type="number"
+                    step={0.01}
+                    min={0}
+                    className="w-full bg-white/5 border border-white/10 rounded px-3 py-1.5 text-white outline-none"
+                    value={range.in}
+                    onChange={(e) => setRange({ ...range, in: parseFloat(e.target.value) || 0 })}
+                  />
+                </div>
+                <div className="flex-1 space-y-2">
+                  <span className="text-zinc-500 text-[10px]">Out (s)</span>
+                  <input
+                    type="number"
+                    step={0.01}
+                    min={0}
+                    className="w-full bg-white/5 border border-white/10 rounded px-3 py-1.5 text-white outline-none"
+                    value={range.out}
+                    onChange={(e) => setRange({ ...range, out: parseFloat(e.target.value) || 0 })}
+                  />
+                </div>
+              </div>
+            )}
+          </div>
+
+          {/* Section: Loudness */}
+          <div className="space-y-3">
+            <label className="text-zinc-400 font-medium flex items-center gap-2">
+              <Volume2 size={14} /> Loudness
+            </label>
+            <label className="flex items-center gap-2 text-zinc-300">
+              <input type="checkbox" checked={normalize} onChange={(e) => setNormalize(e.target.checked)} />
+              Normalize loudness (EBU R128)
+            </label>
+            {normalize && (
+              <div className="flex gap-4 items-end">
+                <div className="flex-1 space-y-2">
+                  <span className="text-zinc-500 text-[10px]">Integrated (LUFS)</span>
+                  <select
+                    className="w-full bg-white/5 border border-white/10 rounded px-2 py-1.5 text-white outline-none appearance-none"
+                    value={loudness.integrated}
+                    onChange={(e) => setLoudness({ ...loudness, integrated: parseFloat(e.target.value) })}
+                  >
+                    <option value={-14} className="bg-[#0f0f0f]">-14 LUFS (Streaming)</option>
+                    <option value={-16} className="bg-[#0f0f0f]">-16 LUFS (Podcast)</option>
+                    <option value={-23} className="bg-[#0f0f0f]">-23 LUFS (Broadcast)</option>
+                  </select>
+                </div>
+                <div className="flex-1 space-y-2">
+                  <span className="text-zinc-500 text-[10px]">True peak (dBTP)</span>
+                  <input
+                    type="number"
+                    step={0.1}
+                    className="w-full bg-white/5 border border-white/10 rounded px-3 py-1.5 text-white outline-none"
+                    value={loudness.truePeak}
+                    onChange={(e) => setLoudness({ ...loudness, truePeak: parseFloat(e.target.value) || 0 })}
+                  />
+                </div>
+              </div>
+            )}
+          </div>
+
+          {/* Section: Audio tracks (roles e ducking vão junto com as tracks) */}
+          {audioTracks.length > 0 && (
+            <div className="space-y-3">
+              <label className="text-zinc-400 font-medium flex items-center gap-2">
+                <Volume2 size={14} /> Audio Tracks
+              </label>
+              {audioTracks.map((t, i) => (
+                <div key={t.id} className="flex items-center gap-3">
+                  <span className="text-zinc-500 w-16">A{i + 1}</span>
+                  <select
+                    className="flex-1 bg-white/5 border border-white/10 rounded px-2 py-1 text-white outline-none appearance-none"
+                    value={t.role ?? ''}
+                    onChange={(e) => updateTrack(t.id, { role: (e.target.value || undefined) as ExportTrack['role'] })}
+                  >
+                    <option value="" className="bg-[#0f0f0f]">No role</option>
+                    <option value="dialogue" className="bg-[#0f0f0f]">Dialogue</option>
+                    <option value="music" className="bg-[#0f0f0f]">Music</option>
+                    <option value="effects" className="bg-[#0f0f0f]">Effects</option>
+                  </select>
+                  <label className={`flex items-center gap-2 ${t.role === 'music' ? 'text-zinc-300' : 'text-zinc-600'}`}>
+                    <input
+                      type="checkbox"
+                      disabled={t.role !== 'music'}
+                      checked={t.role === 'music' && (t.ducking?.enabled ?? false)}
+                      onChange={(e) => updateTrack(t.id, { ducking: { ...t.ducking, enabled: e.target.checked } })}
+                    />
+                    Duck under dialogue
+                  </label>
+                </div>
+              ))}
+            </div>
+          )}
+
+        </div>
+
+        {/* Modal Footer */}
+        <div className="p-4 bg-white/5 flex justify-end gap-3 border-t border-white/5">
+          <button
+            onClick={onClose}
+            className="px-4 py-1.5 rounded text-zinc-400 hover:text-white transition-all text-[11px]"
+          >
+            Cancel
+          </button>
+          <button
+            onClick={handleExport}
+            disabled={!validRange}
+            className="px-6 py-1.5 rounded bg-blue-600 hover:bg-blue-500 disabled:opacity-40 text-white font-medium transition-all text-[11px] shadow-lg shadow-blue-900/20"
+          >
+            Export
+          </button>
+        </div>
+
+      </div>
+    </div>
+  );
+};
+
+export default ExportSettingsModal;
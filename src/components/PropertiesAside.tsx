@@ -58,16 +58,25 @@ export const PropertiesAside = ({
 
   const assetnow = assets.find(a => a.name === foundClip.name);
 
-  const selectedClip = {
-    ...foundClip,
-    path: assetnow?.path,
-    type: knowTypeByAssetName(foundClip.name)
-  };
+  // Text clips have no asset behind them
+  const selectedClip = foundClip.type === 'text'
+    ? foundClip
+    : {
+      ...foundClip,
+      path: assetnow?.path,
+      type: knowTypeByAssetName(foundClip.name)
+    };
 
-  if (!selectedClip.path) return null;
+  if (!selectedClip.path && selectedClip.type !== 'text') return null;
 
   const activeHex = COLOR_MAP[selectedClip.color] || '#4f46e5';
 
+  const updateText = (changes: Record<string, any>) => {
+    setClips(prev => prev.map(c =>
+      c.id === selectedClip.id ? { ...c, text: { ...c.text, ...changes } } : c
+    ));
+  };
+
   const VIDEO_EXTENSIONS = ['.mp4', '.mkv', '.mov', '.avi', '.webm', '.m4v'];
   const AUDIO_EXTENSIONS = ['.mp3', '.wav', '.ogg', '.flac', '.aac', '.m4a'];
   const IMAGE_EXTENSIONS = ['.jpg', '.jpeg', '.png', '.gif', '.webp', '.svg', '.bmp'];
@@ -202,16 +211,59 @@ export const PropertiesAside = ({
 
           {isText && (
             <>
-              <PropertyRow label="Font" keyframable={false}>
-                <select className="bg-white/5 border border-white/5 rounded px-2 py-1 text-[10px] text-white outline-none w-full">
-                  <option>Inter</option>
-                  <option>Roboto</option>
-                  <option>Monospace</option>
-                </select>
-              </PropertyRow>
-              <PropertyRow label="Color" activeColor={activeHex}>
-                <input type="color" className="w-full h-8 bg-transparent border-none rounded cursor-pointer" />
+              <PropertyRow label="Text" keyframable={false}>
+                <textarea
+                  rows={3}
+                  value={selectedClip.text?.content ?? ''}
+                  onChange={(e) => updateText({ content: e.target.value })}
+                  onKeyDown={(e) => e.stopPropagation()}
+                  className="w-full bg-white/5 border border-white/5 rounded px-2 py-1 text-[10px] text-white outline-none focus:border-white/20 resize-none"
+                />
               </PropertyRow>
+              <div className="grid grid-cols-2 gap-2">
+                <PropertyRow label="Font Size" keyframable={false}>
+                  <input
+                    type="number"
+                    min={1}
+                    placeholder="Auto"
+                    value={selectedClip.text?.fontSize ?? ''}
+                    onChange={(e) => updateText({ fontSize: parseFloat(e.target.value) || undefined })}
+                    className="w-full bg-white/5 border border-white/5 rounded px-2 py-1 text-[10px] text-white outline-none"
+                  />
+                </PropertyRow>
+                <PropertyRow label="Color" keyframable={false}>
+                  <input
+                    type="color"
+                    value={selectedClip.text?.color ?? '#ffffff'}
+                    onChange={(e) => updateText({ color: e.target.value })}
+                    className="w-full h-6 bg-transparent border-none rounded cursor-pointer"
+                  />
+                </PropertyRow>
+              </div>
+              <div className="grid grid-cols-2 gap-2">
+                <PropertyRow label="Align" keyframable={false}>
+                  <select
+                    value={selectedClip.text?.align ?? 'center'}
+                    onChange={(e) => updateText({ align: e.target.value })}
+                    className="bg-white/5 border border-white/5 rounded px-2 py-1 text-[10px] text-white outline-none w-full"
+                  >
+                    <option value="left" className="bg-[#090909]">Left</option>
+                    <option value="center" className="bg-[#090909]">Center</option>
+                    <option value="right" className="bg-[#090909]">Right</option>
+                  </select>
+                </PropertyRow>
+                <PropertyRow label="Vertical" keyframable={false}>
+                  <select
+                    value={selectedClip.text?.verticalAlign ?? 'middle'}
+                    onChange={(e) => updateText({ verticalAlign: e.target.value })}
+                    className="bg-white/5 border border-white/5 rounded px-2 py-1 text-[10px] text-white outline-none w-full"
+                  >
+                    <option value="top" className="bg-[#090909]">Top</option>
+                    <option value="middle" className="bg-[#090909]">Middle</option>
+                    <option value="bottom" className="bg-[#090909]">Bottom</option>
+                  </select>
+                </PropertyRow>
+              </div>
             </>
           )}
 
@@ -454,6 +506,24 @@ export const PropertiesAside = ({
     </div>
   </div>
 </PropertyRow>
+            {/\.(gif|webp)$/i.test(selectedClip.name) && (
+              <PropertyRow label="Loop Animation" keyframable={false}>
+                <label className="flex items-center gap-2 text-[10px] text-zinc-300 cursor-pointer">
+                  <input
+                    type="checkbox"
+                    checked={selectedClip.loop ?? true}
+                    onChange={(e) => {
+                      e.stopPropagation();
+                      const loop = e.target.checked;
+                      setClips(prev => prev.map(c =>
+                        c.id === selectedClip.id ? { ...c, loop } : c
+                      ));
+                    }}
+                  />
+                  {(selectedClip.loop ?? true) ? 'Repeat to fill the clip' : 'Hold the last frame'}
+                </label>
+              </PropertyRow>
+            )}
             <PropertyRow label="Mask" keyframable={false}>
               <button className="w-full bg-white/5 border border-white/5 rounded py-2 text-[9px] font-bold hover:bg-white/10 transition-all uppercase">
                 Edit Mask